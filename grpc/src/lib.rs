@@ -0,0 +1,8 @@
+//! Generated code of `proto/bc_orm/v1/wallet.proto`. Connect with
+//! `v1::wallet_service_client::WalletServiceClient`; the `bc_orm` crate
+//! implements `v1::wallet_service_server::WalletService` with its `grpc`
+//! feature.
+
+pub mod v1 {
+    tonic::include_proto!("bc_orm.v1");
+}
@@ -0,0 +1,208 @@
+use std::sync::Arc;
+
+use sea_orm::{ColumnTrait, Condition, QueryFilter};
+
+use crate::{
+    entity::{prelude::*, *},
+    repo::{
+        ApiClient, Balance, BatchTransfer, EdgeWallet, ListEdges, ListTokens, ListWallets,
+        OpenEscrow, Page, ProvisionWallet, Repo, RepoError, Scope, TransferToken, Wallet,
+    },
+};
+
+/// `Repo` as seen by one API client: every operation checks the client's
+/// scopes and edges before it reaches the client's tenant.
+#[derive(Clone)]
+pub struct AuthorizedRepo {
+    repo: Arc<Repo>,
+    client: ApiClient,
+}
+
+impl AuthorizedRepo {
+    pub fn new(repo: &Repo, client: ApiClient) -> Self {
+        AuthorizedRepo {
+            repo: repo.for_tenant(client.tenant_id),
+            client,
+        }
+    }
+
+    /// Authenticate the bearer token of an `Authorization` header value.
+    pub async fn authenticate(
+        repo: Arc<Repo>,
+        authorization: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let key = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| {
+                anyhow::anyhow!(RepoError::Unauthenticated(
+                    "missing bearer API key".to_string()
+                ))
+            })?;
+        let client = repo.authenticate(key).await?;
+        Ok(AuthorizedRepo::new(&repo, client))
+    }
+
+    pub fn client(&self) -> &ApiClient {
+        &self.client
+    }
+
+    /// The client's tenant, without any authorization check.
+    pub fn repo(&self) -> &Arc<Repo> {
+        &self.repo
+    }
+
+    pub async fn provision_wallet(&self, data: ProvisionWallet) -> anyhow::Result<EdgeWallet> {
+        self.client.require_edge(Scope::Provision, data.edge_id)?;
+        self.repo.clone().provision_wallet(data).await
+    }
+
+    pub async fn deprovision_edge(&self, edge_id: i32) -> anyhow::Result<EdgeWallet> {
+        self.client.require_edge(Scope::Provision, edge_id)?;
+        self.repo.clone().deprovision_edge(edge_id).await
+    }
+
+    pub async fn transfer_token(&self, data: TransferToken) -> anyhow::Result<EdgeWallet> {
+        self.client.require_edge(Scope::Transfer, data.edge_id)?;
+        self.repo.clone().transfer_token(data).await
+    }
+
+    pub async fn get_edge_wallet(&self, edge_id: i32) -> anyhow::Result<EdgeWallet> {
+        self.client.require_edge(Scope::Read, edge_id)?;
+        self.repo.get_edge_wallet(edge_id).await
+    }
+
+    pub async fn list_edges(&self, query: ListEdges) -> anyhow::Result<Page<EdgeWallet>> {
+        self.client.require_scope(Scope::Read)?;
+        self.repo.list_edges(self.restrict(query)).await
+    }
+
+    pub async fn list_edge_records(
+        &self,
+        query: ListEdges,
+    ) -> anyhow::Result<Page<edges_to_wallets::Model>> {
+        self.client.require_scope(Scope::Read)?;
+        self.repo.list_edge_records(self.restrict(query)).await
+    }
+
+    pub async fn list_wallets(&self, query: ListWallets) -> anyhow::Result<Page<Wallet>> {
+        self.client.require_every_edge(Scope::Read)?;
+        self.repo.list_wallets(query).await
+    }
+
+    pub async fn list_wallet_records(
+        &self,
+        query: ListWallets,
+    ) -> anyhow::Result<Page<wallets::Model>> {
+        self.client.require_every_edge(Scope::Read)?;
+        self.repo.list_wallet_records(query).await
+    }
+
+    pub async fn get_wallet_balances(&self, wallet_id: i32) -> anyhow::Result<Vec<Balance>> {
+        self.require_wallet(Scope::Read, wallet_id).await?;
+        self.repo.get_wallet_balances(wallet_id).await
+    }
+
+    pub async fn list_tokens(&self, query: ListTokens) -> anyhow::Result<Page<tokens::Model>> {
+        self.client.require_every_edge(Scope::Read)?;
+        self.repo.list_tokens(query).await
+    }
+
+    pub async fn batch_transfer(&self, data: BatchTransfer) -> anyhow::Result<Vec<Wallet>> {
+        self.require_wallet(Scope::Transfer, data.from_wallet_id)
+            .await?;
+        self.repo.clone().batch_transfer(data).await
+    }
+
+    pub async fn open_escrow(&self, data: OpenEscrow) -> anyhow::Result<escrows::Model> {
+        self.client.require_edge(Scope::Transfer, data.edge_id)?;
+        self.repo.clone().open_escrow(data).await
+    }
+
+    pub async fn release_escrow(&self, escrow_id: i32) -> anyhow::Result<escrows::Model> {
+        self.require_escrow(escrow_id).await?;
+        self.repo.clone().release_escrow(escrow_id).await
+    }
+
+    pub async fn refund_escrow(&self, escrow_id: i32) -> anyhow::Result<escrows::Model> {
+        self.require_escrow(escrow_id).await?;
+        self.repo.clone().refund_escrow(escrow_id).await
+    }
+
+    /// Narrow a listing to the client's edges.
+    fn restrict(&self, mut query: ListEdges) -> ListEdges {
+        if let Some(allowed) = &self.client.edge_ids {
+            query.edge_ids = Some(match query.edge_ids {
+                Some(edge_ids) => edge_ids
+                    .into_iter()
+                    .filter(|edge_id| allowed.contains(edge_id))
+                    .collect(),
+                None => allowed.clone(),
+            });
+        }
+        query
+    }
+
+    /// A wallet is accessible through the edge owning it.
+    pub async fn require_wallet(&self, scope: Scope, wallet_id: i32) -> anyhow::Result<()> {
+        if self.client.edge_ids.is_none() {
+            return self.client.require_scope(scope);
+        }
+
+        let edge = self
+            .repo
+            .find::<EdgesToWallets>()
+            .filter(
+                Condition::any()
+                    .add(edges_to_wallets::Column::SrcWalletId.eq(wallet_id))
+                    .add(edges_to_wallets::Column::DstWalletId.eq(wallet_id))
+                    .add(edges_to_wallets::Column::NftWalletId.eq(wallet_id)),
+            )
+            .one(&self.repo.db)
+            .await?;
+        match edge {
+            Some(edge) => self.client.require_edge(scope, edge.edge_id),
+            None => anyhow::bail!(RepoError::Forbidden(format!(
+                "API client may not access wallet {wallet_id}"
+            ))),
+        }
+    }
+
+    /// The wallets of the client's edges, every wallet when `None`.
+    pub async fn wallet_ids(&self, scope: Scope) -> anyhow::Result<Option<Vec<i32>>> {
+        self.client.require_scope(scope)?;
+        let Some(edge_ids) = &self.client.edge_ids else {
+            return Ok(None);
+        };
+
+        let edges = self
+            .repo
+            .find::<EdgesToWallets>()
+            .filter(edges_to_wallets::Column::EdgeId.is_in(edge_ids.clone()))
+            .all(&self.repo.db)
+            .await?;
+        Ok(Some(
+            edges
+                .into_iter()
+                .flat_map(|edge| [edge.src_wallet_id, edge.dst_wallet_id, edge.nft_wallet_id])
+                .collect(),
+        ))
+    }
+
+    async fn require_escrow(&self, escrow_id: i32) -> anyhow::Result<()> {
+        if self.client.edge_ids.is_none() {
+            return self.client.require_scope(Scope::Transfer);
+        }
+
+        let escrow = self
+            .repo
+            .find_by_id::<Escrows, _>(escrow_id)
+            .one(&self.repo.db)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!(RepoError::NotFound("escrow_id not found".to_string()))
+            })?;
+        self.client.require_edge(Scope::Transfer, escrow.edge_id)
+    }
+}
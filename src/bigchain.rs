@@ -0,0 +1,432 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use bigchaindb::{
+    ed25519_keypair,
+    transaction::{Transaction, UnspentOutput},
+};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, RequestBuilder, StatusCode,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::ledger::{Block, KeyPair, Ledger, LedgerError, OutputRef};
+
+/// How reads pick the node to try first. Writes always go to the first
+/// healthy node in configuration order.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeSelection {
+    /// Spread reads over the healthy nodes.
+    #[default]
+    RoundRobin,
+    /// Read from the first healthy node in configuration order.
+    Priority,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct NodeStats {
+    pub url: String,
+    pub healthy: bool,
+    pub requests: u64,
+    pub failures: u64,
+}
+
+/// Settings of the HTTP client shared by every ledger call.
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// Whole request timeout, including waiting for a commit.
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    /// Sent with every request, e.g. `app_id` and `app_key`.
+    pub headers: HashMap<String, String>,
+}
+
+impl ClientOptions {
+    pub fn build(&self) -> anyhow::Result<Client> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in self.headers.iter() {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| anyhow::anyhow!("invalid header name {name}"))?,
+                HeaderValue::from_str(value)
+                    .map_err(|_| anyhow::anyhow!("invalid value of header {name}"))?,
+            );
+        }
+
+        let mut builder = Client::builder().default_headers(headers);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+struct Node {
+    /// API root, e.g. `http://localhost:9984/api/v1`, without trailing `/`.
+    url: String,
+    requests: AtomicU64,
+    failures: AtomicU64,
+    /// Set on failure, cleared on the next success.
+    failed_at: Mutex<Option<Instant>>,
+}
+
+impl Node {
+    fn healthy(&self, cooldown: Duration) -> bool {
+        match *self.failed_at.lock().unwrap() {
+            Some(failed_at) => failed_at.elapsed() >= cooldown,
+            None => true,
+        }
+    }
+}
+
+/// Why a call to a node failed.
+enum CallError {
+    /// No connection to the node could be made, so the request was never
+    /// sent: try the next one.
+    Unreachable(anyhow::Error),
+    /// The node timed out, answered with a server error or with a body that
+    /// does not parse, after receiving the request: retry a read on the next
+    /// one, but not a write, which the node may have applied.
+    Node(anyhow::Error),
+    /// The node rejected the request itself, another node would too.
+    Request(anyhow::Error),
+}
+
+/// Ledger client shared by every `Repo` operation: one HTTP client, and so
+/// one connection pool, over a set of BigchainDB nodes with health
+/// tracking. A node failing a request is put aside for `cooldown` and the
+/// request is retried on the next node, so a single dead node does not take
+/// provisioning down. Writes are only retried when they never reached the
+/// failing node. Nodes put aside are still tried last when every node is
+/// failing.
+pub struct NodePool {
+    client: Client,
+    nodes: Vec<Node>,
+    selection: NodeSelection,
+    cooldown: Duration,
+    next: AtomicUsize,
+}
+
+impl NodePool {
+    pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+    pub fn new(urls: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        NodePool {
+            client: Client::new(),
+            nodes: urls
+                .into_iter()
+                .map(|url| Node {
+                    url: url.into().trim_end_matches('/').to_string(),
+                    requests: AtomicU64::new(0),
+                    failures: AtomicU64::new(0),
+                    failed_at: Mutex::new(None),
+                })
+                .collect(),
+            selection: NodeSelection::default(),
+            cooldown: Self::DEFAULT_COOLDOWN,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Use a client configured elsewhere, see [`ClientOptions`].
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn selection(mut self, selection: NodeSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// A committed transaction in the typed form the bigchaindb crate
+    /// builds TRANSFERs from.
+    async fn fetch_transaction(&self, transaction_id: &str) -> anyhow::Result<Transaction> {
+        self.call(true, |url| {
+            self.client
+                .get(format!("{url}/transactions/{transaction_id}"))
+        })
+        .await
+    }
+
+    /// Post a signed transaction and wait for it to be committed. It is
+    /// only posted to another node when the previous ones could not be
+    /// reached at all. When a node fails after receiving it, it may still
+    /// have committed it, so the transaction is looked up by id instead and
+    /// found means committed.
+    async fn post_transaction_commit(&self, tx: Transaction) -> anyhow::Result<Transaction> {
+        let posted = self
+            .call(false, |url| {
+                self.client
+                    .post(format!("{url}/transactions"))
+                    .query(&[("mode", "commit")])
+                    .json(&tx)
+            })
+            .await;
+        match (posted, &tx.id) {
+            (Err(e), Some(id)) if matches!(e.downcast_ref(), Some(LedgerError::Unconfirmed(_))) => {
+                match self.fetch_transaction(id).await {
+                    Ok(committed) => Ok(committed),
+                    Err(lookup) => {
+                        Err(e.context(format!("transaction {id} not found: {lookup:#}")))
+                    }
+                }
+            }
+            (posted, _) => posted,
+        }
+    }
+
+    /// Node indices in the order a request tries them: healthy nodes first,
+    /// starting from the selected one, then the nodes put aside.
+    fn candidates(&self, read: bool) -> Vec<usize> {
+        let len = self.nodes.len();
+        let start = match (read, self.selection) {
+            (true, NodeSelection::RoundRobin) if len > 0 => {
+                self.next.fetch_add(1, Ordering::Relaxed) % len
+            }
+            _ => 0,
+        };
+        let (healthy, unhealthy): (Vec<usize>, Vec<usize>) = (0..len)
+            .map(|i| (start + i) % len)
+            .partition(|&i| self.nodes[i].healthy(self.cooldown));
+        healthy.into_iter().chain(unhealthy).collect()
+    }
+
+    async fn call<T, F>(&self, read: bool, request: F) -> anyhow::Result<T>
+    where
+        T: DeserializeOwned,
+        F: Fn(&str) -> RequestBuilder,
+    {
+        let mut last_err = None;
+        for i in self.candidates(read) {
+            let node = &self.nodes[i];
+            node.requests.fetch_add(1, Ordering::Relaxed);
+
+            match send(request(&node.url)).await {
+                Ok(value) => {
+                    *node.failed_at.lock().unwrap() = None;
+                    return Ok(value);
+                }
+                Err(CallError::Request(e)) => {
+                    *node.failed_at.lock().unwrap() = None;
+                    return Err(e);
+                }
+                Err(CallError::Unreachable(e) | CallError::Node(e)) if read => {
+                    self.put_aside(node, &e);
+                    last_err = Some(e);
+                }
+                Err(CallError::Unreachable(e)) => {
+                    self.put_aside(node, &e);
+                    last_err = Some(e);
+                }
+                Err(CallError::Node(e)) => {
+                    self.put_aside(node, &e);
+                    return Err(LedgerError::Unconfirmed(format!("{e:#}")).into());
+                }
+            }
+        }
+
+        match last_err {
+            Some(e) => {
+                Err(LedgerError::Unavailable(format!("every BigchainDB node failed: {e:#}")).into())
+            }
+            None => {
+                Err(LedgerError::Unavailable("no BigchainDB node configured".to_string()).into())
+            }
+        }
+    }
+
+    fn put_aside(&self, node: &Node, e: &anyhow::Error) {
+        node.failures.fetch_add(1, Ordering::Relaxed);
+        *node.failed_at.lock().unwrap() = Some(Instant::now());
+        log::warn!("BigchainDB node {} failed: {e}", node.url);
+    }
+}
+
+#[async_trait]
+impl Ledger for NodePool {
+    fn generate_keypair(&self) -> KeyPair {
+        let keypair = ed25519_keypair();
+        KeyPair {
+            public_key: keypair.pk,
+            private_key: keypair.sk,
+        }
+    }
+
+    async fn create(
+        &self,
+        owner: &KeyPair,
+        amount: i32,
+        asset: Option<serde_json::Value>,
+        metadata: Option<serde_json::Value>,
+    ) -> anyhow::Result<String> {
+        let condition = Transaction::make_ed25519_condition(&owner.public_key, true)
+            .ok_or_else(|| invalid_public_key(&owner.public_key))?;
+        let output = Transaction::make_output(condition, amount.to_string());
+        let tx = Transaction::make_create_transaction(
+            asset,
+            metadata,
+            vec![output],
+            vec![owner.private_key.to_string()],
+        );
+        let signed_tx = Transaction::sign_transaction(&tx, vec![&owner.private_key]);
+
+        let tx = self.post_transaction_commit(signed_tx).await?;
+        tx.id.ok_or_else(committed_without_id)
+    }
+
+    async fn transfer(
+        &self,
+        owner: &KeyPair,
+        _token: &str,
+        inputs: &[OutputRef],
+        outputs: &[(String, i32)],
+        metadata: serde_json::Value,
+    ) -> anyhow::Result<String> {
+        let mut unspent_outputs = Vec::new();
+        for input in inputs {
+            unspent_outputs.push(UnspentOutput {
+                tx: self.fetch_transaction(&input.transaction_id).await?,
+                output_index: input.output_index,
+            });
+        }
+
+        let outputs = outputs
+            .iter()
+            .map(|(public_key, amount)| {
+                let condition = Transaction::make_ed25519_condition(public_key, true)
+                    .ok_or_else(|| invalid_public_key(public_key))?;
+                Ok(Transaction::make_output(condition, amount.to_string()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let transfer_tx =
+            Transaction::make_transfer_transaction(unspent_outputs, outputs, Some(metadata));
+
+        // one signature per input
+        let signed_tx = Transaction::sign_transaction(
+            &transfer_tx,
+            vec![owner.private_key.as_str(); inputs.len()],
+        );
+
+        let tx = self.post_transaction_commit(signed_tx).await?;
+        tx.id.ok_or_else(committed_without_id)
+    }
+
+    async fn list_outputs(
+        &self,
+        public_key: &str,
+        spent: Option<bool>,
+    ) -> anyhow::Result<Vec<OutputRef>> {
+        self.call(true, |url| {
+            let mut query = vec![("public_key", public_key.to_string())];
+            if let Some(spent) = spent {
+                query.push(("spent", spent.to_string()));
+            }
+            self.client.get(format!("{url}/outputs")).query(&query)
+        })
+        .await
+    }
+
+    async fn get_transaction(
+        &self,
+        transaction_id: &str,
+    ) -> anyhow::Result<crate::ledger::Transaction> {
+        self.call(true, |url| {
+            self.client
+                .get(format!("{url}/transactions/{transaction_id}"))
+        })
+        .await
+    }
+
+    async fn get_block(&self, height: i64) -> anyhow::Result<Option<Block>> {
+        match self
+            .call(true, |url| {
+                self.client.get(format!("{url}/blocks/{height}"))
+            })
+            .await
+        {
+            Ok(block) => Ok(Some(block)),
+            Err(e) if matches!(e.downcast_ref(), Some(LedgerError::NotFound(_))) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// As advertised by the API root of a healthy node.
+    async fn stream_url(&self) -> anyhow::Result<String> {
+        let root: serde_json::Value = self.call(true, |url| self.client.get(url)).await?;
+        root["streams"].as_str().map(str::to_string).ok_or_else(|| {
+            anyhow::anyhow!(LedgerError::Unavailable(
+                "BigchainDB API root advertises no stream".to_string()
+            ))
+        })
+    }
+
+    fn stats(&self) -> Vec<NodeStats> {
+        self.nodes
+            .iter()
+            .map(|node| NodeStats {
+                url: node.url.clone(),
+                healthy: node.healthy(self.cooldown),
+                requests: node.requests.load(Ordering::Relaxed),
+                failures: node.failures.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+/// Keys come from the database, the configuration and callers, so a
+/// malformed one is an error of the request rather than a panic.
+fn invalid_public_key(public_key: &str) -> anyhow::Error {
+    anyhow::anyhow!(LedgerError::Rejected(format!(
+        "invalid public key {public_key}"
+    )))
+}
+
+/// The node committed the transaction but did not say which id it has.
+fn committed_without_id() -> anyhow::Error {
+    anyhow::anyhow!(LedgerError::Unconfirmed(
+        "committed transaction has no id".to_string()
+    ))
+}
+
+async fn send<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, CallError> {
+    let response = request.send().await.map_err(|e| match e.is_connect() {
+        true => CallError::Unreachable(e.into()),
+        false => CallError::Node(e.into()),
+    })?;
+
+    let status = response.status();
+    if status.is_server_error() {
+        return Err(CallError::Node(anyhow::anyhow!("BigchainDB {status}")));
+    }
+    if status == StatusCode::NOT_FOUND {
+        let path = response.url().path().to_string();
+        return Err(CallError::Request(LedgerError::NotFound(path).into()));
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(CallError::Request(
+            LedgerError::Rejected(format!("BigchainDB {status}: {body}")).into(),
+        ));
+    }
+
+    response.json().await.map_err(|e| CallError::Node(e.into()))
+}
@@ -0,0 +1,116 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
+};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::repo::EdgeWallet;
+
+/// Storage behind [`EdgeCache`]. Implement it to keep edge wallets in an
+/// external store shared between instances. Edge ids are only unique within
+/// a tenant, so entries are keyed by both.
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    async fn get(&self, tenant_id: i32, edge_id: i32) -> Option<EdgeWallet>;
+    async fn put(&self, tenant_id: i32, edge_wallet: EdgeWallet);
+    async fn remove(&self, tenant_id: i32, edge_id: i32);
+    async fn clear(&self);
+}
+
+/// Process local [`CacheStore`].
+#[derive(Default)]
+pub struct InMemoryStore {
+    edge_wallets: RwLock<HashMap<(i32, i32), EdgeWallet>>,
+}
+
+#[async_trait]
+impl CacheStore for InMemoryStore {
+    async fn get(&self, tenant_id: i32, edge_id: i32) -> Option<EdgeWallet> {
+        self.edge_wallets
+            .read()
+            .unwrap()
+            .get(&(tenant_id, edge_id))
+            .cloned()
+    }
+
+    async fn put(&self, tenant_id: i32, edge_wallet: EdgeWallet) {
+        self.edge_wallets
+            .write()
+            .unwrap()
+            .insert((tenant_id, edge_wallet.edge_id), edge_wallet);
+    }
+
+    async fn remove(&self, tenant_id: i32, edge_id: i32) {
+        self.edge_wallets
+            .write()
+            .unwrap()
+            .remove(&(tenant_id, edge_id));
+    }
+
+    async fn clear(&self) {
+        self.edge_wallets.write().unwrap().clear();
+    }
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Read-through cache of [`EdgeWallet`]s keyed by tenant and edge id,
+/// counting hits and misses whatever the store.
+pub struct EdgeCache {
+    store: Box<dyn CacheStore>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl EdgeCache {
+    pub fn new(store: impl CacheStore + 'static) -> Self {
+        EdgeCache {
+            store: Box::new(store),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn in_memory() -> Self {
+        Self::new(InMemoryStore::default())
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) async fn get(&self, tenant_id: i32, edge_id: i32) -> Option<EdgeWallet> {
+        let edge_wallet = self.store.get(tenant_id, edge_id).await;
+        let counter = match edge_wallet {
+            Some(_) => &self.hits,
+            None => &self.misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        edge_wallet
+    }
+
+    pub(crate) async fn put(&self, tenant_id: i32, edge_wallet: EdgeWallet) {
+        self.store.put(tenant_id, edge_wallet).await
+    }
+
+    pub(crate) async fn invalidate(&self, tenant_id: i32, edge_id: i32) {
+        self.store.remove(tenant_id, edge_id).await
+    }
+
+    pub(crate) async fn invalidate_all(&self) {
+        self.store.clear().await
+    }
+}
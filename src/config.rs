@@ -0,0 +1,430 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    bigchain::{ClientOptions, NodePool, NodeSelection},
+    cache::EdgeCache,
+    connect_with,
+    db::{DbConfig, MigrationMode},
+    events::{EventSink, JsonlSink},
+    ledger::{InMemoryLedger, Ledger},
+    repo::{Repo, RetryPolicy, DEFAULT_TENANT_ID, DELIVERY_LEASE},
+};
+
+/// Settings for the `Repo` and the binary, layered from lowest to highest
+/// precedence: built-in defaults, the TOML file, environment variables and
+/// command-line flags. See `bc_orm.example.toml`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database: DbConfig,
+    pub bigchaindb: BigchainConfig,
+    pub mint: MintConfig,
+    pub treasury: TreasuryConfig,
+    pub cache: CacheConfig,
+    pub events: EventsConfig,
+    pub webhooks: WebhooksConfig,
+    pub ledger_sync: LedgerSyncConfig,
+    pub escrows: EscrowsConfig,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BigchainConfig {
+    /// Keep the ledger in the process instead of on `nodes`, for local
+    /// development. It starts empty on every run.
+    pub in_memory: bool,
+    pub nodes: Vec<String>,
+    pub selection: NodeSelection,
+    /// How long a failing node is skipped, 30 seconds when unset.
+    pub cooldown_secs: Option<u64>,
+    pub timeout_secs: Option<u64>,
+    pub connect_timeout_secs: Option<u64>,
+    /// Sent with every request, e.g. `app_id` and `app_key`.
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MintConfig {
+    /// Units minted for every edge's FT, all held by its `src_wallet`.
+    pub ft_supply: i32,
+    pub metadata: serde_json::Value,
+}
+
+impl Default for MintConfig {
+    fn default() -> Self {
+        MintConfig {
+            ft_supply: 100,
+            metadata: serde_json::json!({ "co": "devr" }),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TreasuryConfig {
+    /// BigchainDB public key receiving balances swept from deprovisioned
+    /// edges, required only to deprovision them.
+    pub public_key: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub enabled: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventsConfig {
+    /// File the servers append published events to, one JSON object per
+    /// line. Events stay in the outbox when unset.
+    pub jsonl_path: Option<PathBuf>,
+    /// Seconds between two drains of the outbox.
+    pub relay_interval_secs: u64,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        EventsConfig {
+            jsonl_path: None,
+            relay_interval_secs: 1,
+        }
+    }
+}
+
+impl EventsConfig {
+    /// The sink events are relayed to, if any.
+    pub async fn sink(&self) -> anyhow::Result<Option<Arc<dyn EventSink>>> {
+        Ok(match &self.jsonl_path {
+            Some(path) => Some(Arc::new(JsonlSink::open(path).await?)),
+            None => None,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhooksConfig {
+    /// Deliver webhooks while serving.
+    pub enabled: bool,
+    /// Attempts before a delivery goes to the dead letters.
+    pub max_attempts: i32,
+    /// Wait before the first retry, doubled on every further one.
+    pub backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    pub timeout_secs: u64,
+    /// Seconds between two looks for due deliveries.
+    pub poll_interval_secs: u64,
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        let policy = RetryPolicy::default();
+        WebhooksConfig {
+            enabled: false,
+            max_attempts: policy.max_attempts,
+            backoff_secs: policy.base_delay.as_secs(),
+            max_backoff_secs: policy.max_delay.as_secs(),
+            timeout_secs: 10,
+            poll_interval_secs: 1,
+        }
+    }
+}
+
+impl WebhooksConfig {
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_attempts,
+            base_delay: Duration::from_secs(self.backoff_secs),
+            max_delay: Duration::from_secs(self.max_backoff_secs),
+        }
+    }
+
+    /// HTTP client posting the deliveries.
+    pub fn client(&self) -> anyhow::Result<reqwest::Client> {
+        Ok(reqwest::Client::builder()
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .build()?)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LedgerSyncConfig {
+    /// Apply ledger transactions touching our wallets to the balances while
+    /// serving.
+    pub enabled: bool,
+    /// Valid transactions websocket stream, the one the nodes advertise
+    /// when unset.
+    pub stream_url: Option<String>,
+    /// Seconds between two attempts to reconnect to the stream.
+    pub reconnect_secs: u64,
+}
+
+impl Default for LedgerSyncConfig {
+    fn default() -> Self {
+        LedgerSyncConfig {
+            enabled: false,
+            stream_url: None,
+            reconnect_secs: 5,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct EscrowsConfig {
+    /// Refund open escrows past their deadline while serving.
+    pub sweep: bool,
+    /// Seconds between two looks for expired escrows.
+    pub sweep_interval_secs: u64,
+}
+
+impl Default for EscrowsConfig {
+    fn default() -> Self {
+        EscrowsConfig {
+            sweep: false,
+            sweep_interval_secs: 60,
+        }
+    }
+}
+
+/// Command-line flags overriding the configuration file. Each one can also
+/// be set through the environment variable next to it.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct ConfigArgs {
+    /// TOML configuration file
+    #[arg(long, short, env = "BC_ORM_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    #[arg(long, env = "DATABASE_URL")]
+    pub database_url: Option<String>,
+
+    #[arg(long, env = "DATABASE_MAX_CONNECTIONS")]
+    pub database_max_connections: Option<u32>,
+
+    #[arg(long, env = "DATABASE_SCHEMA")]
+    pub database_schema: Option<String>,
+
+    /// apply, verify or skip pending migrations on connect
+    #[arg(long, env = "DATABASE_MIGRATIONS")]
+    pub database_migrations: Option<MigrationMode>,
+
+    /// BigchainDB node URL, repeat or comma-separate for several nodes
+    #[arg(
+        long = "bigchaindb-node",
+        env = "BIGCHAINDB_NODES",
+        value_delimiter = ','
+    )]
+    pub bigchaindb_nodes: Vec<String>,
+
+    /// Use a ledger in the process instead of BigchainDB nodes
+    #[arg(long, env = "IN_MEMORY_LEDGER")]
+    pub in_memory_ledger: Option<bool>,
+
+    #[arg(long, env = "MINT_FT_SUPPLY")]
+    pub mint_ft_supply: Option<i32>,
+
+    #[arg(long, env = "TREASURY_PUBLIC_KEY")]
+    pub treasury_public_key: Option<String>,
+
+    #[arg(long, env = "EDGE_CACHE")]
+    pub cache: Option<bool>,
+
+    /// File the servers append published events to
+    #[arg(long, env = "EVENTS_JSONL")]
+    pub events_jsonl: Option<PathBuf>,
+
+    /// Deliver webhooks while serving
+    #[arg(long, env = "WEBHOOKS")]
+    pub webhooks: Option<bool>,
+
+    /// Sync balances from the ledger while serving
+    #[arg(long, env = "LEDGER_SYNC")]
+    pub ledger_sync: Option<bool>,
+
+    /// BigchainDB valid transactions websocket stream
+    #[arg(long, env = "LEDGER_STREAM_URL")]
+    pub ledger_stream_url: Option<String>,
+
+    /// Refund expired escrows while serving
+    #[arg(long, env = "ESCROW_SWEEP")]
+    pub escrow_sweep: Option<bool>,
+}
+
+impl Config {
+    /// Read a TOML configuration file.
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("read config {}: {e}", path.display()))?;
+        toml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("parse config {}: {e}", path.display()))
+    }
+
+    /// Load the file named by `args` (if any), apply the overrides from
+    /// `args` and validate the result.
+    pub fn load(args: &ConfigArgs) -> anyhow::Result<Self> {
+        let config = Self::resolve(args)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Like [`Config::load`] without validation, for commands needing only
+    /// part of the settings.
+    pub fn resolve(args: &ConfigArgs) -> anyhow::Result<Self> {
+        let mut config = match &args.config {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply(args);
+        Ok(config)
+    }
+
+    fn apply(&mut self, args: &ConfigArgs) {
+        if let Some(url) = &args.database_url {
+            self.database.url = url.clone();
+        }
+        if let Some(max_connections) = args.database_max_connections {
+            self.database.max_connections = Some(max_connections);
+        }
+        if let Some(schema) = &args.database_schema {
+            self.database.schema = Some(schema.clone());
+        }
+        if let Some(migrations) = args.database_migrations {
+            self.database.migrations = migrations;
+        }
+        if !args.bigchaindb_nodes.is_empty() {
+            self.bigchaindb.nodes = args.bigchaindb_nodes.clone();
+        }
+        if let Some(in_memory) = args.in_memory_ledger {
+            self.bigchaindb.in_memory = in_memory;
+        }
+        if let Some(ft_supply) = args.mint_ft_supply {
+            self.mint.ft_supply = ft_supply;
+        }
+        if let Some(public_key) = &args.treasury_public_key {
+            self.treasury.public_key = Some(public_key.clone());
+        }
+        if let Some(enabled) = args.cache {
+            self.cache.enabled = enabled;
+        }
+        if let Some(path) = &args.events_jsonl {
+            self.events.jsonl_path = Some(path.clone());
+        }
+        if let Some(enabled) = args.webhooks {
+            self.webhooks.enabled = enabled;
+        }
+        if let Some(enabled) = args.ledger_sync {
+            self.ledger_sync.enabled = enabled;
+        }
+        if let Some(stream_url) = &args.ledger_stream_url {
+            self.ledger_sync.stream_url = Some(stream_url.clone());
+        }
+        if let Some(sweep) = args.escrow_sweep {
+            self.escrows.sweep = sweep;
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.database.validate()?;
+        if self.bigchaindb.nodes.is_empty() && !self.bigchaindb.in_memory {
+            anyhow::bail!("bigchaindb.nodes needs at least one node");
+        }
+        for node in self.bigchaindb.nodes.iter() {
+            if !(node.starts_with("http://") || node.starts_with("https://")) {
+                anyhow::bail!("bigchaindb node {node} is not an http(s) URL");
+            }
+        }
+        if self.mint.ft_supply <= 0 {
+            anyhow::bail!("mint.ft_supply must be positive");
+        }
+        if let Some(public_key) = &self.treasury.public_key {
+            if public_key.is_empty()
+                || !public_key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() && !matches!(c, '0' | 'O' | 'I' | 'l'))
+            {
+                anyhow::bail!("treasury.public_key is not a base58 key");
+            }
+        }
+        if self.events.relay_interval_secs == 0 {
+            anyhow::bail!("events.relay_interval_secs must be positive");
+        }
+        if self.webhooks.max_attempts <= 0 {
+            anyhow::bail!("webhooks.max_attempts must be positive");
+        }
+        if self.webhooks.poll_interval_secs == 0 {
+            anyhow::bail!("webhooks.poll_interval_secs must be positive");
+        }
+        if self.webhooks.timeout_secs >= DELIVERY_LEASE.as_secs() {
+            anyhow::bail!(
+                "webhooks.timeout_secs must be below {}",
+                DELIVERY_LEASE.as_secs()
+            );
+        }
+        if let Some(stream_url) = &self.ledger_sync.stream_url {
+            if !(stream_url.starts_with("ws://") || stream_url.starts_with("wss://")) {
+                anyhow::bail!("ledger_sync.stream_url {stream_url} is not a ws(s) URL");
+            }
+        }
+        if self.ledger_sync.reconnect_secs == 0 {
+            anyhow::bail!("ledger_sync.reconnect_secs must be positive");
+        }
+        if self.ledger_sync.enabled && self.bigchaindb.in_memory {
+            anyhow::bail!("ledger_sync needs BigchainDB nodes, the in-memory ledger has no stream");
+        }
+        if self.escrows.sweep_interval_secs == 0 {
+            anyhow::bail!("escrows.sweep_interval_secs must be positive");
+        }
+        Ok(())
+    }
+
+    /// Connect to the database, run pending migrations and build the `Repo`.
+    pub async fn build_repo(&self) -> anyhow::Result<Repo> {
+        let ledger: Arc<dyn Ledger> = match self.bigchaindb.in_memory {
+            true => Arc::new(InMemoryLedger::new()),
+            false => Arc::new(self.node_pool()?),
+        };
+        let db = connect_with(&self.database).await?;
+
+        Ok(Repo {
+            db,
+            ledger,
+            ft_supply: self.mint.ft_supply,
+            mint_metadata: self.mint.metadata.clone(),
+            treasury_public_key: self.treasury.public_key.clone(),
+            cache: self.cache.enabled.then(|| Arc::new(EdgeCache::in_memory())),
+            tenant_id: DEFAULT_TENANT_ID,
+        })
+    }
+
+    fn node_pool(&self) -> anyhow::Result<NodePool> {
+        let client = ClientOptions {
+            timeout: self.bigchaindb.timeout_secs.map(Duration::from_secs),
+            connect_timeout: self
+                .bigchaindb
+                .connect_timeout_secs
+                .map(Duration::from_secs),
+            headers: self.bigchaindb.headers.clone(),
+        }
+        .build()?;
+
+        Ok(NodePool::new(self.bigchaindb.nodes.iter().cloned())
+            .client(client)
+            .selection(self.bigchaindb.selection)
+            .cooldown(
+                self.bigchaindb
+                    .cooldown_secs
+                    .map_or(NodePool::DEFAULT_COOLDOWN, Duration::from_secs),
+            ))
+    }
+}
@@ -0,0 +1,136 @@
+use std::{env, str::FromStr, time::Duration};
+
+use sea_orm::{ConnectOptions, Database, DatabaseConnection, DbErr};
+use serde::Deserialize;
+
+/// What connecting does about pending schema migrations.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationMode {
+    /// Apply them.
+    #[default]
+    Apply,
+    /// Fail if any is pending, for instances that must not migrate.
+    Verify,
+    /// Ignore them.
+    Skip,
+}
+
+impl FromStr for MigrationMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "apply" => Ok(Self::Apply),
+            "verify" => Ok(Self::Verify),
+            "skip" => Ok(Self::Skip),
+            _ => anyhow::bail!("unknown migration mode {s}, expected apply, verify or skip"),
+        }
+    }
+}
+
+/// Database connection settings. Everything but `url` falls back to the
+/// sea-orm defaults when unset.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DbConfig {
+    pub url: String,
+    pub max_connections: Option<u32>,
+    pub min_connections: Option<u32>,
+    pub connect_timeout_secs: Option<u64>,
+    pub acquire_timeout_secs: Option<u64>,
+    pub idle_timeout_secs: Option<u64>,
+    pub max_lifetime_secs: Option<u64>,
+    /// Log every statement through sqlx.
+    pub sqlx_logging: Option<bool>,
+    pub sqlx_logging_level: Option<log::LevelFilter>,
+    /// Postgres `search_path`, e.g. `"bc"` or `"bc,public"`.
+    pub schema: Option<String>,
+    #[serde(default)]
+    pub migrations: MigrationMode,
+}
+
+impl DbConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        DbConfig {
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Read the settings from `DATABASE_URL`, `DATABASE_MAX_CONNECTIONS`,
+    /// `DATABASE_SCHEMA`, ... , one variable per field.
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(DbConfig {
+            url: env::var("DATABASE_URL")
+                .map_err(|_| anyhow::anyhow!("DATABASE_URL is not set"))?,
+            max_connections: env_var("DATABASE_MAX_CONNECTIONS")?,
+            min_connections: env_var("DATABASE_MIN_CONNECTIONS")?,
+            connect_timeout_secs: env_var("DATABASE_CONNECT_TIMEOUT_SECS")?,
+            acquire_timeout_secs: env_var("DATABASE_ACQUIRE_TIMEOUT_SECS")?,
+            idle_timeout_secs: env_var("DATABASE_IDLE_TIMEOUT_SECS")?,
+            max_lifetime_secs: env_var("DATABASE_MAX_LIFETIME_SECS")?,
+            sqlx_logging: env_var("DATABASE_SQLX_LOGGING")?,
+            sqlx_logging_level: env_var("DATABASE_SQLX_LOGGING_LEVEL")?,
+            schema: env_var("DATABASE_SCHEMA")?,
+            migrations: env_var("DATABASE_MIGRATIONS")?.unwrap_or_default(),
+        })
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.url.is_empty() {
+            anyhow::bail!("database.url is required");
+        }
+        if let (Some(min), Some(max)) = (self.min_connections, self.max_connections) {
+            if min > max {
+                anyhow::bail!("database.min_connections is greater than database.max_connections");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn connect_options(&self) -> ConnectOptions {
+        let mut opt = ConnectOptions::new(self.url.clone());
+        if let Some(max_connections) = self.max_connections {
+            opt.max_connections(max_connections);
+        }
+        if let Some(min_connections) = self.min_connections {
+            opt.min_connections(min_connections);
+        }
+        if let Some(secs) = self.connect_timeout_secs {
+            opt.connect_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.acquire_timeout_secs {
+            opt.acquire_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.idle_timeout_secs {
+            opt.idle_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.max_lifetime_secs {
+            opt.max_lifetime(Duration::from_secs(secs));
+        }
+        if let Some(sqlx_logging) = self.sqlx_logging {
+            opt.sqlx_logging(sqlx_logging);
+        }
+        if let Some(level) = self.sqlx_logging_level {
+            opt.sqlx_logging_level(level);
+        }
+        if let Some(schema) = &self.schema {
+            opt.set_schema_search_path(schema.clone());
+        }
+        opt
+    }
+
+    pub async fn connect(&self) -> Result<DatabaseConnection, DbErr> {
+        Database::connect(self.connect_options()).await
+    }
+}
+
+fn env_var<T: FromStr>(key: &str) -> anyhow::Result<Option<T>> {
+    match env::var(key) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow::anyhow!("invalid {key}: {value}")),
+        Err(_) => Ok(None),
+    }
+}
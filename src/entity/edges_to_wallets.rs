@@ -12,6 +12,11 @@ pub struct Model {
     pub src_wallet_id: i32,
     pub dst_wallet_id: i32,
     pub nft_wallet_id: i32,
+    pub closed_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+    #[serde(skip_serializing)]
+    pub tenant_id: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -41,5 +46,3 @@ pub enum Relation {
     )]
     Wallets1,
 }
-
-impl ActiveModelBehavior for ActiveModel {}
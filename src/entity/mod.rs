@@ -2,7 +2,16 @@
 
 pub mod prelude;
 
+pub mod api_clients;
+pub mod balance_changes;
 pub mod edges_to_wallets;
+pub mod escrows;
+pub mod ledger_cursors;
+pub mod ledger_transactions;
+pub mod outbox_events;
+pub mod sea_orm_active_enums;
 pub mod tokens;
 pub mod wallets;
 pub mod wallets_to_tokens;
+pub mod webhook_deliveries;
+pub mod webhook_subscriptions;
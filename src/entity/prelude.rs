@@ -1,6 +1,14 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+pub use super::api_clients::Entity as ApiClients;
+pub use super::balance_changes::Entity as BalanceChanges;
 pub use super::edges_to_wallets::Entity as EdgesToWallets;
+pub use super::escrows::Entity as Escrows;
+pub use super::ledger_cursors::Entity as LedgerCursors;
+pub use super::ledger_transactions::Entity as LedgerTransactions;
+pub use super::outbox_events::Entity as OutboxEvents;
 pub use super::tokens::Entity as Tokens;
 pub use super::wallets::Entity as Wallets;
 pub use super::wallets_to_tokens::Entity as WalletsToTokens;
+pub use super::webhook_deliveries::Entity as WebhookDeliveries;
+pub use super::webhook_subscriptions::Entity as WebhookSubscriptions;
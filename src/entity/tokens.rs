@@ -1,23 +1,57 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+use super::sea_orm_active_enums::TokenKind;
 use sea_orm::entity::prelude::*;
 use serde::Serialize;
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, ToSchema)]
 #[sea_orm(table_name = "tokens")]
+#[schema(as = Token)]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     #[sea_orm(column_type = "Text")]
     pub token: String,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    #[schema(value_type = Option<Object>)]
+    pub asset: Option<Json>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    #[schema(value_type = Option<Object>)]
+    pub metadata: Option<Json>,
+    pub supply: Option<i32>,
+    pub kind: Option<TokenKind>,
+    pub creator_wallet_id: Option<i32>,
+    #[schema(value_type = String, format = DateTime)]
+    pub created_at: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    pub updated_at: DateTimeWithTimeZone,
+    #[serde(skip_serializing)]
+    pub tenant_id: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::escrows::Entity")]
+    Escrows,
+    #[sea_orm(
+        belongs_to = "super::wallets::Entity",
+        from = "Column::CreatorWalletId",
+        to = "super::wallets::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    Wallets,
     #[sea_orm(has_many = "super::wallets_to_tokens::Entity")]
     WalletsToTokens,
 }
 
+impl Related<super::escrows::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Escrows.def()
+    }
+}
+
 impl Related<super::wallets_to_tokens::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::WalletsToTokens.def()
@@ -32,5 +66,3 @@ impl Related<super::wallets::Entity> for Entity {
         Some(super::wallets_to_tokens::Relation::Tokens.def().rev())
     }
 }
-
-impl ActiveModelBehavior for ActiveModel {}
@@ -12,14 +12,27 @@ pub struct Model {
     pub public_key: String,
     #[sea_orm(column_type = "Text")]
     pub private_key: String,
+    pub closed_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+    #[serde(skip_serializing)]
+    pub tenant_id: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::escrows::Entity")]
+    Escrows,
     #[sea_orm(has_many = "super::wallets_to_tokens::Entity")]
     WalletsToTokens,
 }
 
+impl Related<super::escrows::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Escrows.def()
+    }
+}
+
 impl Related<super::wallets_to_tokens::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::WalletsToTokens.def()
@@ -34,5 +47,3 @@ impl Related<super::tokens::Entity> for Entity {
         Some(super::wallets_to_tokens::Relation::Wallets.def().rev())
     }
 }
-
-impl ActiveModelBehavior for ActiveModel {}
@@ -11,6 +11,10 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub token_id: i32,
     pub volume: i32,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+    #[serde(skip_serializing)]
+    pub tenant_id: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -44,5 +48,3 @@ impl Related<super::wallets::Entity> for Entity {
         Relation::Wallets.def()
     }
 }
-
-impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,237 @@
+use std::{fmt, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::{broadcast, Mutex},
+};
+
+use crate::entity::outbox_events;
+
+/// Something that happened to the edges, wallets and tokens of a tenant.
+/// Tokens are identified by their BigchainDB asset id and wallets by their
+/// public key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    EdgeProvisioned {
+        edge_id: i32,
+        token: String,
+        nft: String,
+        src_public_key: String,
+        dst_public_key: String,
+    },
+    EdgeDeprovisioned {
+        edge_id: i32,
+    },
+    /// FT units moved on the ledger, `edge_id` is `None` for batch payouts.
+    TokensTransferred {
+        edge_id: Option<i32>,
+        token: String,
+        from_public_key: String,
+        to_public_key: String,
+        amount: i32,
+    },
+    NftTransferred {
+        edge_id: i32,
+        nft: String,
+        from_public_key: String,
+        to_public_key: String,
+    },
+    EscrowOpened {
+        escrow_id: i32,
+        edge_id: i32,
+        amount: i32,
+        deadline: DateTimeWithTimeZone,
+    },
+    EscrowReleased {
+        escrow_id: i32,
+        edge_id: i32,
+        amount: i32,
+    },
+    EscrowRefunded {
+        escrow_id: i32,
+        edge_id: i32,
+        amount: i32,
+    },
+}
+
+impl Event {
+    pub fn event_type(&self) -> EventType {
+        match self {
+            Event::EdgeProvisioned { .. } => EventType::EdgeProvisioned,
+            Event::EdgeDeprovisioned { .. } => EventType::EdgeDeprovisioned,
+            Event::TokensTransferred { .. } => EventType::TokensTransferred,
+            Event::NftTransferred { .. } => EventType::NftTransferred,
+            Event::EscrowOpened { .. } => EventType::EscrowOpened,
+            Event::EscrowReleased { .. } => EventType::EscrowReleased,
+            Event::EscrowRefunded { .. } => EventType::EscrowRefunded,
+        }
+    }
+
+    /// The edge the event is about, if any.
+    pub fn edge_id(&self) -> Option<i32> {
+        match self {
+            Event::EdgeProvisioned { edge_id, .. }
+            | Event::EdgeDeprovisioned { edge_id }
+            | Event::NftTransferred { edge_id, .. }
+            | Event::EscrowOpened { edge_id, .. }
+            | Event::EscrowReleased { edge_id, .. }
+            | Event::EscrowRefunded { edge_id, .. } => Some(*edge_id),
+            Event::TokensTransferred { edge_id, .. } => *edge_id,
+        }
+    }
+}
+
+/// The `type` of an [`Event`].
+#[derive(Serialize, Deserialize, clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum EventType {
+    EdgeProvisioned,
+    EdgeDeprovisioned,
+    TokensTransferred,
+    NftTransferred,
+    EscrowOpened,
+    EscrowReleased,
+    EscrowRefunded,
+}
+
+impl EventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventType::EdgeProvisioned => "edge_provisioned",
+            EventType::EdgeDeprovisioned => "edge_deprovisioned",
+            EventType::TokensTransferred => "tokens_transferred",
+            EventType::NftTransferred => "nft_transferred",
+            EventType::EscrowOpened => "escrow_opened",
+            EventType::EscrowReleased => "escrow_released",
+            EventType::EscrowRefunded => "escrow_refunded",
+        }
+    }
+}
+
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An [`Event`] as recorded in the outbox, `id` growing in recording order.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EventRecord {
+    pub id: i32,
+    pub tenant_id: i32,
+    pub occurred_at: DateTimeWithTimeZone,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+impl TryFrom<outbox_events::Model> for EventRecord {
+    type Error = anyhow::Error;
+
+    fn try_from(model: outbox_events::Model) -> anyhow::Result<Self> {
+        Ok(EventRecord {
+            id: model.id,
+            tenant_id: model.tenant_id,
+            occurred_at: model.created_at,
+            event: serde_json::from_value(model.event)
+                .map_err(|e| anyhow::anyhow!("outbox event {}: {e}", model.id))?,
+        })
+    }
+}
+
+/// Destination of the events relayed from the outbox, see
+/// [`Repo::publish_events`](crate::repo::Repo::publish_events). An event is
+/// published again when the relay stops before recording that the sink
+/// accepted it, so sinks should tolerate duplicates by `id`.
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    async fn publish(&self, event: &EventRecord) -> anyhow::Result<()>;
+}
+
+/// Hands events to the subscribers of an in-process broadcast channel.
+/// Events published while nobody is subscribed are dropped, and a subscriber
+/// lagging more than `capacity` events behind loses the oldest ones.
+pub struct BroadcastSink {
+    sender: broadcast::Sender<EventRecord>,
+}
+
+impl BroadcastSink {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        BroadcastSink { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EventRecord> {
+        self.sender.subscribe()
+    }
+}
+
+#[async_trait]
+impl EventSink for BroadcastSink {
+    async fn publish(&self, event: &EventRecord) -> anyhow::Result<()> {
+        // no subscriber is not a failure
+        let _ = self.sender.send(event.clone());
+        Ok(())
+    }
+}
+
+/// Hands every event to each of `sinks` in turn. An event one of them
+/// fails on is published again to all of them.
+pub struct FanoutSink {
+    sinks: Vec<Arc<dyn EventSink>>,
+}
+
+impl FanoutSink {
+    pub fn new(sinks: Vec<Arc<dyn EventSink>>) -> Self {
+        FanoutSink { sinks }
+    }
+}
+
+#[async_trait]
+impl EventSink for FanoutSink {
+    async fn publish(&self, event: &EventRecord) -> anyhow::Result<()> {
+        for sink in self.sinks.iter() {
+            sink.publish(event).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Appends every event to a file as one JSON object per line.
+pub struct JsonlSink {
+    file: Mutex<File>,
+}
+
+impl JsonlSink {
+    /// Open `path` for appending, creating it if needed.
+    pub async fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(|e| anyhow::anyhow!("open event log {}: {e}", path.display()))?;
+        Ok(JsonlSink {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for JsonlSink {
+    async fn publish(&self, event: &EventRecord) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
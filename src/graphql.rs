@@ -0,0 +1,543 @@
+use std::sync::Arc;
+
+use async_graphql::{
+    http::GraphiQLSource, Context, EmptySubscription, Enum, ErrorExtensions, InputObject, Json,
+    Object, Schema, SimpleObject,
+};
+use axum::{
+    extract::{FromRef, State},
+    response::{Html, IntoResponse},
+    routing::get,
+    Router,
+};
+use sea_orm::{prelude::DateTimeWithTimeZone, ColumnTrait, ModelTrait, QueryFilter, QueryOrder};
+
+use crate::{
+    auth::AuthorizedRepo,
+    entity::{prelude::*, sea_orm_active_enums::TokenKind, *},
+    repo::{
+        ListEdges, ListTokens, ListWallets, ProvisionWallet, Repo, RepoError, Scope, SortBy,
+        SortOrder,
+    },
+};
+
+pub type BcSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+/// Requests must carry the caller's [`AuthorizedRepo`] as data, as
+/// [`router`] does.
+pub fn schema() -> BcSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription).finish()
+}
+
+#[derive(Clone, FromRef)]
+struct GraphqlState {
+    schema: BcSchema,
+    repo: Arc<Repo>,
+}
+
+/// `POST /graphql` executing queries and `GET /graphql` serving GraphiQL.
+/// Queries run on behalf of the API client authenticated by the request.
+pub fn router(repo: Arc<Repo>) -> Router {
+    Router::new()
+        .route("/graphql", get(graphiql).post(execute))
+        .with_state(GraphqlState {
+            schema: schema(),
+            repo,
+        })
+}
+
+async fn graphiql() -> impl IntoResponse {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+async fn execute(
+    State(schema): State<BcSchema>,
+    authorized: AuthorizedRepo,
+    axum::Json(request): axum::Json<async_graphql::Request>,
+) -> axum::Json<async_graphql::Response> {
+    axum::Json(schema.execute(request.data(authorized)).await)
+}
+
+type Result<T> = async_graphql::Result<T>;
+
+/// Turn a repo failure into a GraphQL error with an `extensions.code`,
+/// hiding database and ledger details.
+fn gql_error(e: anyhow::Error) -> async_graphql::Error {
+    let code = e.chain().find_map(|cause| {
+        cause.downcast_ref::<RepoError>().map(|e| match e {
+            RepoError::Unauthenticated(_) => "UNAUTHENTICATED",
+            RepoError::Forbidden(_) => "FORBIDDEN",
+            RepoError::NotFound(_) => "NOT_FOUND",
+            RepoError::Invalid(_) => "BAD_REQUEST",
+            RepoError::Conflict(_) => "CONFLICT",
+            RepoError::InsufficientFunds(_) => "INSUFFICIENT_FUNDS",
+        })
+    });
+    match code {
+        Some(code) => async_graphql::Error::new(format!("{e:#}")).extend_with(|_, ext| {
+            ext.set("code", code);
+        }),
+        None => {
+            log::error!("request failed: {e:#}");
+            async_graphql::Error::new("internal error").extend_with(|_, ext| {
+                ext.set("code", "INTERNAL");
+            })
+        }
+    }
+}
+
+fn db_error(e: sea_orm::DbErr) -> async_graphql::Error {
+    gql_error(e.into())
+}
+
+/// The requesting client's view of the repo. Root fields go through it, and
+/// so does every wallet or balance reached through a relation, since a
+/// relation can lead to another edge's.
+fn authorized<'a>(ctx: &Context<'a>) -> &'a AuthorizedRepo {
+    ctx.data_unchecked::<AuthorizedRepo>()
+}
+
+/// The requesting client's tenant.
+fn repo<'a>(ctx: &Context<'a>) -> &'a Arc<Repo> {
+    authorized(ctx).repo()
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq, Default)]
+#[graphql(name = "SortOrder")]
+enum GqlSortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl From<GqlSortOrder> for SortOrder {
+    fn from(order: GqlSortOrder) -> Self {
+        match order {
+            GqlSortOrder::Asc => SortOrder::Asc,
+            GqlSortOrder::Desc => SortOrder::Desc,
+        }
+    }
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq, Default)]
+#[graphql(name = "SortBy")]
+enum GqlSortBy {
+    #[default]
+    Id,
+    CreatedAt,
+    /// The volume of the filtered token, wallets only.
+    Volume,
+}
+
+impl From<GqlSortBy> for SortBy {
+    fn from(sort: GqlSortBy) -> Self {
+        match sort {
+            GqlSortBy::Id => SortBy::Id,
+            GqlSortBy::CreatedAt => SortBy::CreatedAt,
+            GqlSortBy::Volume => SortBy::Volume,
+        }
+    }
+}
+
+#[derive(InputObject, Default)]
+struct EdgeFilter {
+    /// Only edges whose FT or NFT is this token.
+    token: Option<String>,
+    /// Only edges owning a wallet with this public key.
+    public_key: Option<String>,
+    closed: Option<bool>,
+}
+
+#[derive(InputObject, Default)]
+struct WalletFilter {
+    /// Only wallets holding this token.
+    token: Option<String>,
+    public_key: Option<String>,
+    min_volume: Option<i32>,
+    max_volume: Option<i32>,
+}
+
+#[derive(InputObject, Default)]
+struct TokenFilter {
+    token: Option<String>,
+    /// Only tokens held by a wallet with this public key.
+    public_key: Option<String>,
+}
+
+/// A page of results. Pass `nextCursor` back as `after` for the next page.
+#[derive(SimpleObject)]
+#[graphql(concrete(name = "EdgePage", params(Edge)))]
+#[graphql(concrete(name = "WalletPage", params(Wallet)))]
+#[graphql(concrete(name = "TokenPage", params(Token)))]
+struct Page<T: async_graphql::OutputType> {
+    items: Vec<T>,
+    next_cursor: Option<String>,
+}
+
+impl<T: async_graphql::OutputType> Page<T> {
+    fn from_repo<M>(page: crate::repo::Page<M>, item: impl Fn(M) -> T) -> Self {
+        Page {
+            items: page.items.into_iter().map(item).collect(),
+            next_cursor: page.next_cursor,
+        }
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object(name = "Query")]
+impl QueryRoot {
+    async fn edge(&self, ctx: &Context<'_>, edge_id: i32) -> Result<Option<Edge>> {
+        authorized(ctx)
+            .client()
+            .require_edge(Scope::Read, edge_id)
+            .map_err(gql_error)?;
+        find_edge(ctx, edge_id).await
+    }
+
+    async fn edges(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] filter: EdgeFilter,
+        #[graphql(default)] sort: GqlSortBy,
+        #[graphql(default)] order: GqlSortOrder,
+        after: Option<String>,
+        first: Option<u64>,
+    ) -> Result<Page<Edge>> {
+        let page = authorized(ctx)
+            .list_edge_records(ListEdges {
+                token: filter.token,
+                public_key: filter.public_key,
+                closed: filter.closed,
+                sort: sort.into(),
+                order: order.into(),
+                after,
+                limit: first,
+                edge_ids: None,
+            })
+            .await
+            .map_err(gql_error)?;
+        Ok(Page::from_repo(page, Edge))
+    }
+
+    async fn wallet(&self, ctx: &Context<'_>, id: i32) -> Result<Option<Wallet>> {
+        authorized(ctx)
+            .require_wallet(Scope::Read, id)
+            .await
+            .map_err(gql_error)?;
+        let wallet = repo(ctx)
+            .find_by_id::<Wallets, _>(id)
+            .one(&repo(ctx).db)
+            .await
+            .map_err(db_error)?;
+        Ok(wallet.map(Wallet))
+    }
+
+    async fn wallets(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] filter: WalletFilter,
+        #[graphql(default)] sort: GqlSortBy,
+        #[graphql(default)] order: GqlSortOrder,
+        after: Option<String>,
+        first: Option<u64>,
+    ) -> Result<Page<Wallet>> {
+        let page = authorized(ctx)
+            .list_wallet_records(ListWallets {
+                token: filter.token,
+                public_key: filter.public_key,
+                min_volume: filter.min_volume,
+                max_volume: filter.max_volume,
+                sort: sort.into(),
+                order: order.into(),
+                after,
+                limit: first,
+            })
+            .await
+            .map_err(gql_error)?;
+        Ok(Page::from_repo(page, Wallet))
+    }
+
+    async fn token(&self, ctx: &Context<'_>, token: String) -> Result<Option<Token>> {
+        let page = authorized(ctx)
+            .list_tokens(ListTokens {
+                token: Some(token),
+                limit: Some(1),
+                ..Default::default()
+            })
+            .await
+            .map_err(gql_error)?;
+        Ok(page.items.into_iter().next().map(Token))
+    }
+
+    async fn tokens(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] filter: TokenFilter,
+        #[graphql(default)] sort: GqlSortBy,
+        #[graphql(default)] order: GqlSortOrder,
+        after: Option<String>,
+        first: Option<u64>,
+    ) -> Result<Page<Token>> {
+        let page = authorized(ctx)
+            .list_tokens(ListTokens {
+                token: filter.token,
+                public_key: filter.public_key,
+                sort: sort.into(),
+                order: order.into(),
+                after,
+                limit: first,
+            })
+            .await
+            .map_err(gql_error)?;
+        Ok(Page::from_repo(page, Token))
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object(name = "Mutation")]
+impl MutationRoot {
+    /// Mint an edge's FT and NFT and create its wallets.
+    async fn provision_wallet(
+        &self,
+        ctx: &Context<'_>,
+        edge_id: i32,
+        asset: Json<serde_json::Value>,
+    ) -> Result<Edge> {
+        if !asset.is_object() {
+            return Err(gql_error(anyhow::anyhow!(RepoError::Invalid(
+                "asset must be a JSON object".to_string()
+            ))));
+        }
+        authorized(ctx)
+            .provision_wallet(ProvisionWallet {
+                edge_id,
+                asset: asset.0,
+            })
+            .await
+            .map_err(gql_error)?;
+        require_edge(ctx, edge_id).await
+    }
+
+    /// Transfer one FT unit from an edge's `srcWallet` to its `dstWallet`.
+    async fn transfer_token(&self, ctx: &Context<'_>, edge_id: i32) -> Result<Edge> {
+        authorized(ctx)
+            .transfer_token(crate::repo::TransferToken { edge_id })
+            .await
+            .map_err(gql_error)?;
+        require_edge(ctx, edge_id).await
+    }
+}
+
+async fn find_edge(ctx: &Context<'_>, edge_id: i32) -> Result<Option<Edge>> {
+    let edge = repo(ctx)
+        .find::<EdgesToWallets>()
+        .filter(edges_to_wallets::Column::EdgeId.eq(edge_id))
+        .one(&repo(ctx).db)
+        .await
+        .map_err(db_error)?;
+    Ok(edge.map(Edge))
+}
+
+async fn require_edge(ctx: &Context<'_>, edge_id: i32) -> Result<Edge> {
+    find_edge(ctx, edge_id).await?.ok_or_else(|| {
+        gql_error(anyhow::anyhow!(RepoError::NotFound(
+            "edge_id not found".to_string()
+        )))
+    })
+}
+
+async fn load_wallet(ctx: &Context<'_>, wallet_id: i32) -> Result<Wallet> {
+    authorized(ctx)
+        .require_wallet(Scope::Read, wallet_id)
+        .await
+        .map_err(gql_error)?;
+    repo(ctx)
+        .find_by_id::<Wallets, _>(wallet_id)
+        .one(&repo(ctx).db)
+        .await
+        .map_err(db_error)?
+        .map(Wallet)
+        .ok_or_else(|| "wallet not found".into())
+}
+
+pub struct Edge(edges_to_wallets::Model);
+
+#[Object]
+impl Edge {
+    async fn edge_id(&self) -> i32 {
+        self.0.edge_id
+    }
+
+    async fn closed_at(&self) -> Option<DateTimeWithTimeZone> {
+        self.0.closed_at
+    }
+
+    async fn created_at(&self) -> DateTimeWithTimeZone {
+        self.0.created_at
+    }
+
+    async fn src_wallet(&self, ctx: &Context<'_>) -> Result<Wallet> {
+        load_wallet(ctx, self.0.src_wallet_id).await
+    }
+
+    async fn dst_wallet(&self, ctx: &Context<'_>) -> Result<Wallet> {
+        load_wallet(ctx, self.0.dst_wallet_id).await
+    }
+
+    async fn nft_wallet(&self, ctx: &Context<'_>) -> Result<Wallet> {
+        load_wallet(ctx, self.0.nft_wallet_id).await
+    }
+
+    /// The edge's FT, minted by `srcWallet`.
+    async fn token(&self, ctx: &Context<'_>) -> Result<Option<Token>> {
+        minted_by(ctx, self.0.src_wallet_id).await
+    }
+
+    async fn nft(&self, ctx: &Context<'_>) -> Result<Option<Token>> {
+        minted_by(ctx, self.0.nft_wallet_id).await
+    }
+}
+
+async fn minted_by(ctx: &Context<'_>, wallet_id: i32) -> Result<Option<Token>> {
+    let token = repo(ctx)
+        .find::<Tokens>()
+        .filter(tokens::Column::CreatorWalletId.eq(wallet_id))
+        .order_by_asc(tokens::Column::Id)
+        .one(&repo(ctx).db)
+        .await
+        .map_err(db_error)?;
+    Ok(token.map(Token))
+}
+
+/// The public side of a wallet: its private key has no field.
+pub struct Wallet(wallets::Model);
+
+#[Object]
+impl Wallet {
+    async fn id(&self) -> i32 {
+        self.0.id
+    }
+
+    async fn public_key(&self) -> &str {
+        &self.0.public_key
+    }
+
+    async fn closed_at(&self) -> Option<DateTimeWithTimeZone> {
+        self.0.closed_at
+    }
+
+    async fn balances(&self, ctx: &Context<'_>) -> Result<Vec<Balance>> {
+        let balances = self
+            .0
+            .find_related(WalletsToTokens)
+            .order_by_asc(wallets_to_tokens::Column::TokenId)
+            .all(&repo(ctx).db)
+            .await
+            .map_err(db_error)?;
+        Ok(balances.into_iter().map(Balance).collect())
+    }
+
+    async fn tokens(&self, ctx: &Context<'_>) -> Result<Vec<Token>> {
+        let tokens = self
+            .0
+            .find_related(Tokens)
+            .order_by_asc(tokens::Column::Id)
+            .all(&repo(ctx).db)
+            .await
+            .map_err(db_error)?;
+        Ok(tokens.into_iter().map(Token).collect())
+    }
+}
+
+pub struct Balance(wallets_to_tokens::Model);
+
+#[Object]
+impl Balance {
+    async fn volume(&self) -> i32 {
+        self.0.volume
+    }
+
+    async fn wallet(&self, ctx: &Context<'_>) -> Result<Wallet> {
+        load_wallet(ctx, self.0.wallet_id).await
+    }
+
+    async fn token(&self, ctx: &Context<'_>) -> Result<Token> {
+        repo(ctx)
+            .find_by_id::<Tokens, _>(self.0.token_id)
+            .one(&repo(ctx).db)
+            .await
+            .map_err(db_error)?
+            .map(Token)
+            .ok_or_else(|| "token not found".into())
+    }
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(name = "TokenKind")]
+enum GqlTokenKind {
+    Fungible,
+    NonFungible,
+}
+
+pub struct Token(tokens::Model);
+
+#[Object]
+impl Token {
+    async fn id(&self) -> i32 {
+        self.0.id
+    }
+
+    async fn token(&self) -> &str {
+        &self.0.token
+    }
+
+    async fn kind(&self) -> Option<GqlTokenKind> {
+        self.0.kind.as_ref().map(|kind| match kind {
+            TokenKind::Fungible => GqlTokenKind::Fungible,
+            TokenKind::NonFungible => GqlTokenKind::NonFungible,
+        })
+    }
+
+    async fn supply(&self) -> Option<i32> {
+        self.0.supply
+    }
+
+    async fn asset(&self) -> Option<Json<serde_json::Value>> {
+        self.0.asset.clone().map(Json)
+    }
+
+    async fn metadata(&self) -> Option<Json<serde_json::Value>> {
+        self.0.metadata.clone().map(Json)
+    }
+
+    async fn created_at(&self) -> DateTimeWithTimeZone {
+        self.0.created_at
+    }
+
+    async fn creator(&self, ctx: &Context<'_>) -> Result<Option<Wallet>> {
+        match self.0.creator_wallet_id {
+            Some(wallet_id) => load_wallet(ctx, wallet_id).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Every balance of the token the client may see, largest first.
+    async fn holders(&self, ctx: &Context<'_>) -> Result<Vec<Balance>> {
+        let mut holders = self.0.find_related(WalletsToTokens);
+        if let Some(wallet_ids) = authorized(ctx)
+            .wallet_ids(Scope::Read)
+            .await
+            .map_err(gql_error)?
+        {
+            holders = holders.filter(wallets_to_tokens::Column::WalletId.is_in(wallet_ids));
+        }
+        let balances = holders
+            .order_by_desc(wallets_to_tokens::Column::Volume)
+            .all(&repo(ctx).db)
+            .await
+            .map_err(db_error)?;
+        Ok(balances.into_iter().map(Balance).collect())
+    }
+}
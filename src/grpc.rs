@@ -0,0 +1,282 @@
+use std::{future::Future, net::SocketAddr, sync::Arc};
+
+use bc_orm_grpc::v1::{
+    self,
+    wallet_service_server::{WalletService, WalletServiceServer},
+};
+use sea_orm::{ActiveEnum, DbErr};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Request, Response, Status};
+
+use crate::{
+    auth::AuthorizedRepo,
+    entity::tokens,
+    ledger::LedgerError,
+    repo::{
+        EdgeWallet, ListEdges, ListTokens, ProvisionWallet, Repo, RepoError, SortBy, SortOrder,
+        TransferToken, Wallet,
+    },
+};
+
+/// Items sent ahead of a slow streaming client.
+const STREAM_BUFFER: usize = 64;
+
+/// `WalletService` over a shared `Repo`.
+pub struct GrpcService {
+    repo: Arc<Repo>,
+}
+
+impl GrpcService {
+    pub fn new(repo: Arc<Repo>) -> Self {
+        GrpcService { repo }
+    }
+
+    pub fn into_server(self) -> WalletServiceServer<Self> {
+        WalletServiceServer::new(self)
+    }
+
+    /// Authenticate the `authorization: Bearer <key>` metadata of a call.
+    async fn authorize<T>(&self, request: &Request<T>) -> Result<AuthorizedRepo, Status> {
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok());
+        AuthorizedRepo::authenticate(self.repo.clone(), authorization)
+            .await
+            .map_err(status)
+    }
+}
+
+/// Serve `WalletService` on `addr` until `shutdown` completes.
+pub async fn serve(
+    repo: Arc<Repo>,
+    addr: SocketAddr,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    Server::builder()
+        .add_service(GrpcService::new(repo).into_server())
+        .serve_with_shutdown(addr, shutdown)
+        .await?;
+    Ok(())
+}
+
+#[tonic::async_trait]
+impl WalletService for GrpcService {
+    async fn provision_wallet(
+        &self,
+        request: Request<v1::ProvisionWalletRequest>,
+    ) -> Result<Response<v1::EdgeWallet>, Status> {
+        let repo = self.authorize(&request).await?;
+        let request = request.into_inner();
+        let asset: serde_json::Value = serde_json::from_str(&request.asset_json)
+            .map_err(|e| Status::invalid_argument(format!("asset_json: {e}")))?;
+        if !asset.is_object() {
+            return Err(Status::invalid_argument("asset_json must be a JSON object"));
+        }
+
+        let edge_wallet = repo
+            .provision_wallet(ProvisionWallet {
+                edge_id: request.edge_id,
+                asset,
+            })
+            .await
+            .map_err(status)?;
+        Ok(Response::new(edge_wallet.into()))
+    }
+
+    async fn transfer_token(
+        &self,
+        request: Request<v1::TransferTokenRequest>,
+    ) -> Result<Response<v1::EdgeWallet>, Status> {
+        let repo = self.authorize(&request).await?;
+        let edge_id = request.into_inner().edge_id;
+        let edge_wallet = repo
+            .transfer_token(TransferToken { edge_id })
+            .await
+            .map_err(status)?;
+        Ok(Response::new(edge_wallet.into()))
+    }
+
+    async fn get_edge(
+        &self,
+        request: Request<v1::GetEdgeRequest>,
+    ) -> Result<Response<v1::EdgeWallet>, Status> {
+        let repo = self.authorize(&request).await?;
+        let edge_id = request.into_inner().edge_id;
+        let edge_wallet = repo.get_edge_wallet(edge_id).await.map_err(status)?;
+        Ok(Response::new(edge_wallet.into()))
+    }
+
+    type ListEdgesStream = ReceiverStream<Result<v1::EdgeWallet, Status>>;
+
+    async fn list_edges(
+        &self,
+        request: Request<v1::ListEdgesRequest>,
+    ) -> Result<Response<Self::ListEdgesStream>, Status> {
+        let repo = self.authorize(&request).await?;
+        let request = request.into_inner();
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+
+        // page through the edges until the last page or the client hangs up
+        tokio::spawn(async move {
+            let mut after = None;
+            loop {
+                let page = repo
+                    .list_edges(ListEdges {
+                        token: request.token.clone(),
+                        public_key: request.public_key.clone(),
+                        closed: request.closed,
+                        sort: SortBy::Id,
+                        order: sort_order(request.descending),
+                        after,
+                        limit: None,
+                        edge_ids: None,
+                    })
+                    .await;
+                let page = match page {
+                    Ok(page) => page,
+                    Err(e) => {
+                        let _ = tx.send(Err(status(e))).await;
+                        return;
+                    }
+                };
+                for edge_wallet in page.items {
+                    if tx.send(Ok(edge_wallet.into())).await.is_err() {
+                        return;
+                    }
+                }
+                match page.next_cursor {
+                    Some(cursor) => after = Some(cursor),
+                    None => return,
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    type ListTokensStream = ReceiverStream<Result<v1::Token, Status>>;
+
+    async fn list_tokens(
+        &self,
+        request: Request<v1::ListTokensRequest>,
+    ) -> Result<Response<Self::ListTokensStream>, Status> {
+        let repo = self.authorize(&request).await?;
+        let request = request.into_inner();
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+
+        tokio::spawn(async move {
+            let mut after = None;
+            loop {
+                let page = repo
+                    .list_tokens(ListTokens {
+                        token: request.token.clone(),
+                        public_key: request.public_key.clone(),
+                        sort: SortBy::Id,
+                        order: sort_order(request.descending),
+                        after,
+                        limit: None,
+                    })
+                    .await;
+                let page = match page {
+                    Ok(page) => page,
+                    Err(e) => {
+                        let _ = tx.send(Err(status(e))).await;
+                        return;
+                    }
+                };
+                for token in page.items {
+                    if tx.send(Ok(token.into())).await.is_err() {
+                        return;
+                    }
+                }
+                match page.next_cursor {
+                    Some(cursor) => after = Some(cursor),
+                    None => return,
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+fn sort_order(descending: bool) -> SortOrder {
+    match descending {
+        true => SortOrder::Desc,
+        false => SortOrder::Asc,
+    }
+}
+
+fn status(e: anyhow::Error) -> Status {
+    for cause in e.chain() {
+        if let Some(repo_error) = cause.downcast_ref::<RepoError>() {
+            let message = repo_error.to_string();
+            return match repo_error {
+                RepoError::Unauthenticated(_) => Status::unauthenticated(message),
+                RepoError::Forbidden(_) => Status::permission_denied(message),
+                RepoError::NotFound(_) => Status::not_found(message),
+                RepoError::Invalid(_) => Status::invalid_argument(message),
+                RepoError::Conflict(_) | RepoError::InsufficientFunds(_) => {
+                    Status::failed_precondition(message)
+                }
+            };
+        }
+        if cause.is::<DbErr>() {
+            break;
+        }
+        if cause.is::<LedgerError>() {
+            log::error!("request failed: {e:#}");
+            return Status::unavailable("ledger unavailable");
+        }
+    }
+    // don't leak database details
+    log::error!("request failed: {e:#}");
+    Status::internal("internal error")
+}
+
+impl From<Wallet> for v1::Wallet {
+    fn from(wallet: Wallet) -> Self {
+        v1::Wallet {
+            public_key: wallet.public_key,
+            balances: wallet
+                .balances
+                .into_iter()
+                .map(|balance| v1::Balance {
+                    token: balance.token,
+                    volume: balance.volume,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<EdgeWallet> for v1::EdgeWallet {
+    fn from(edge_wallet: EdgeWallet) -> Self {
+        v1::EdgeWallet {
+            edge_id: edge_wallet.edge_id,
+            src_wallet: Some(edge_wallet.src_wallet.into()),
+            dst_wallet: Some(edge_wallet.dst_wallet.into()),
+            token: edge_wallet.token,
+            nft: edge_wallet.nft,
+            closed_at: edge_wallet
+                .closed_at
+                .map(|closed_at| closed_at.to_rfc3339()),
+        }
+    }
+}
+
+impl From<tokens::Model> for v1::Token {
+    fn from(token: tokens::Model) -> Self {
+        v1::Token {
+            id: token.id,
+            token: token.token,
+            kind: token.kind.map(|kind| kind.to_value()).unwrap_or_default(),
+            supply: token.supply,
+            asset_json: token.asset.map(|asset| asset.to_string()),
+            metadata_json: token.metadata.map(|metadata| metadata.to_string()),
+            created_at: token.created_at.to_rfc3339(),
+        }
+    }
+}
@@ -0,0 +1,544 @@
+use std::{future::Future, sync::Arc};
+
+use axum::{
+    async_trait,
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        FromRef, FromRequest, FromRequestParts, State,
+    },
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use sea_orm::DbErr;
+use serde::Serialize;
+use tokio::net::TcpListener;
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi, ToSchema,
+};
+
+use crate::{
+    auth::AuthorizedRepo,
+    bigchain::NodeStats,
+    cache::CacheStats,
+    entity::{
+        escrows::Model as Escrow,
+        sea_orm_active_enums::{EscrowState, TokenKind},
+        tokens::Model as Token,
+    },
+    ledger::LedgerError,
+    repo::{
+        Balance, BatchTransfer, EdgeWallet, EdgeWalletPage, ListEdges, ListTokens, ListWallets,
+        OpenEscrow, Page, Payout, ProvisionWallet, Repo, RepoError, SortBy, SortOrder, TokenPage,
+        TransferToken, Wallet, WalletPage,
+    },
+};
+
+/// OpenAPI document of [`router`], served at `/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "bc_orm"),
+    modifiers(&BearerAuth),
+    paths(
+        health,
+        list_edges,
+        provision_wallet,
+        get_edge,
+        deprovision_edge,
+        transfer_token,
+        list_wallets,
+        get_wallet_balances,
+        list_tokens,
+        batch_transfer,
+        open_escrow,
+        release_escrow,
+        refund_escrow,
+    ),
+    components(schemas(
+        Health,
+        CacheStats,
+        NodeStats,
+        ErrorBody,
+        ProvisionWallet,
+        EdgeWallet,
+        Wallet,
+        Balance,
+        Token,
+        TokenKind,
+        SortBy,
+        SortOrder,
+        EdgeWalletPage,
+        WalletPage,
+        TokenPage,
+        BatchTransfer,
+        Payout,
+        OpenEscrow,
+        Escrow,
+        EscrowState,
+    ))
+)]
+pub struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// JSON API over a shared `Repo`, plus `/graphql` with the `graphql`
+/// feature.
+pub fn router(repo: Arc<Repo>) -> Router {
+    let router = Router::new()
+        .route("/health", get(health))
+        .route("/edges", get(list_edges).post(provision_wallet))
+        .route("/edges/:edge_id", get(get_edge).delete(deprovision_edge))
+        .route("/edges/:edge_id/transfers", post(transfer_token))
+        .route("/wallets", get(list_wallets))
+        .route("/wallets/:wallet_id/balances", get(get_wallet_balances))
+        .route("/tokens", get(list_tokens))
+        .route("/transfers", post(batch_transfer))
+        .route("/escrows", post(open_escrow))
+        .route("/escrows/:escrow_id/release", post(release_escrow))
+        .route("/escrows/:escrow_id/refund", post(refund_escrow))
+        .route("/openapi.json", get(openapi))
+        .with_state(repo.clone());
+
+    #[cfg(feature = "graphql")]
+    let router = router.merge(crate::graphql::router(repo));
+
+    router
+}
+
+/// Serve [`router`] on `listener` until `shutdown` completes, then let the
+/// requests in flight finish.
+pub async fn serve(
+    repo: Arc<Repo>,
+    listener: TcpListener,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    axum::serve(listener, router(repo))
+        .with_graceful_shutdown(shutdown)
+        .await?;
+    Ok(())
+}
+
+/// Completes on Ctrl-C or, on unix, SIGTERM.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// An error response, rendered as [`ErrorBody`].
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        ApiError {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        let status = status_code(&e);
+        let message = match status {
+            // don't leak database or ledger details
+            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::BAD_GATEWAY => {
+                log::error!("request failed: {e:#}");
+                status.canonical_reason().unwrap_or_default().to_string()
+            }
+            _ => format!("{e:#}"),
+        };
+        ApiError { status, message }
+    }
+}
+
+macro_rules! impl_from_rejection {
+    ($($rejection:ty),*) => {
+        $(impl From<$rejection> for ApiError {
+            fn from(rejection: $rejection) -> Self {
+                ApiError {
+                    status: rejection.status(),
+                    message: rejection.body_text(),
+                }
+            }
+        })*
+    };
+}
+
+impl_from_rejection!(JsonRejection, PathRejection, QueryRejection);
+
+/// Body of every error response.
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(ErrorBody {
+                error: self.message,
+            }),
+        )
+            .into_response()
+    }
+}
+
+fn status_code(e: &anyhow::Error) -> StatusCode {
+    for cause in e.chain() {
+        if let Some(e) = cause.downcast_ref::<RepoError>() {
+            return match e {
+                RepoError::Unauthenticated(_) => StatusCode::UNAUTHORIZED,
+                RepoError::Forbidden(_) => StatusCode::FORBIDDEN,
+                RepoError::NotFound(_) => StatusCode::NOT_FOUND,
+                RepoError::Invalid(_) => StatusCode::BAD_REQUEST,
+                RepoError::Conflict(_) => StatusCode::CONFLICT,
+                RepoError::InsufficientFunds(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            };
+        }
+        if cause.is::<DbErr>() {
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+        if cause.is::<LedgerError>() {
+            return StatusCode::BAD_GATEWAY;
+        }
+    }
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+// axum's extractors, rejecting with an `ApiError` so that malformed requests
+// get the same JSON error body as refused ones
+
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(ApiError))]
+struct Json<T>(T);
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(ApiError))]
+struct Path<T>(T);
+
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(ApiError))]
+struct Query<T>(T);
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+/// Authenticates the `Authorization: Bearer <key>` header of the request.
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthorizedRepo
+where
+    Arc<Repo>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let authorization = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        Ok(AuthorizedRepo::authenticate(Arc::<Repo>::from_ref(state), authorization).await?)
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct Health {
+    cache: Option<CacheStats>,
+    nodes: Vec<NodeStats>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses((status = 200, body = Health))
+)]
+async fn health(State(repo): State<Arc<Repo>>) -> Json<Health> {
+    Json(Health {
+        cache: repo.cache_stats(),
+        nodes: repo.node_stats(),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/edges",
+    tag = "edges",
+    request_body = ProvisionWallet,
+    security(("api_key" = [])),
+    responses(
+        (status = 201, body = EdgeWallet),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 409, body = ErrorBody),
+    )
+)]
+async fn provision_wallet(
+    repo: AuthorizedRepo,
+    Json(data): Json<ProvisionWallet>,
+) -> Result<(StatusCode, Json<EdgeWallet>), ApiError> {
+    if !data.asset.is_object() {
+        return Err(ApiError::bad_request("asset must be a JSON object"));
+    }
+
+    let edge_wallet = repo.provision_wallet(data).await?;
+    Ok((StatusCode::CREATED, Json(edge_wallet)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/edges/{edge_id}/transfers",
+    tag = "edges",
+    params(("edge_id" = i32, Path, description = "Id of the edge")),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, body = EdgeWallet),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+    )
+)]
+async fn transfer_token(repo: AuthorizedRepo, Path(edge_id): Path<i32>) -> ApiResult<EdgeWallet> {
+    Ok(Json(repo.transfer_token(TransferToken { edge_id }).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/edges/{edge_id}",
+    tag = "edges",
+    params(("edge_id" = i32, Path, description = "Id of the edge")),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, body = EdgeWallet),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn get_edge(repo: AuthorizedRepo, Path(edge_id): Path<i32>) -> ApiResult<EdgeWallet> {
+    Ok(Json(repo.get_edge_wallet(edge_id).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/edges/{edge_id}",
+    tag = "edges",
+    params(("edge_id" = i32, Path, description = "Id of the edge")),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, body = EdgeWallet),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, body = ErrorBody),
+    )
+)]
+async fn deprovision_edge(repo: AuthorizedRepo, Path(edge_id): Path<i32>) -> ApiResult<EdgeWallet> {
+    Ok(Json(repo.deprovision_edge(edge_id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/edges",
+    tag = "edges",
+    params(ListEdges),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, body = EdgeWalletPage),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+    )
+)]
+async fn list_edges(
+    repo: AuthorizedRepo,
+    Query(query): Query<ListEdges>,
+) -> ApiResult<Page<EdgeWallet>> {
+    Ok(Json(repo.list_edges(query).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/wallets",
+    tag = "wallets",
+    params(ListWallets),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, body = WalletPage),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+    )
+)]
+async fn list_wallets(
+    repo: AuthorizedRepo,
+    Query(query): Query<ListWallets>,
+) -> ApiResult<Page<Wallet>> {
+    Ok(Json(repo.list_wallets(query).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/wallets/{wallet_id}/balances",
+    tag = "wallets",
+    params(("wallet_id" = i32, Path, description = "Id of the wallet")),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, body = [Balance]),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn get_wallet_balances(
+    repo: AuthorizedRepo,
+    Path(wallet_id): Path<i32>,
+) -> ApiResult<Vec<Balance>> {
+    Ok(Json(repo.get_wallet_balances(wallet_id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tokens",
+    tag = "tokens",
+    params(ListTokens),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, body = TokenPage),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+    )
+)]
+async fn list_tokens(
+    repo: AuthorizedRepo,
+    Query(query): Query<ListTokens>,
+) -> ApiResult<Page<Token>> {
+    Ok(Json(repo.list_tokens(query).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/transfers",
+    tag = "transfers",
+    request_body = BatchTransfer,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, body = [Wallet]),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+    )
+)]
+async fn batch_transfer(
+    repo: AuthorizedRepo,
+    Json(data): Json<BatchTransfer>,
+) -> ApiResult<Vec<Wallet>> {
+    Ok(Json(repo.batch_transfer(data).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/escrows",
+    tag = "escrows",
+    request_body = OpenEscrow,
+    security(("api_key" = [])),
+    responses(
+        (status = 201, body = Escrow),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+    )
+)]
+async fn open_escrow(
+    repo: AuthorizedRepo,
+    Json(data): Json<OpenEscrow>,
+) -> Result<(StatusCode, Json<Escrow>), ApiError> {
+    let escrow = repo.open_escrow(data).await?;
+    Ok((StatusCode::CREATED, Json(escrow)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/escrows/{escrow_id}/release",
+    tag = "escrows",
+    params(("escrow_id" = i32, Path, description = "Id of the escrow")),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, body = Escrow),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, body = ErrorBody),
+    )
+)]
+async fn release_escrow(repo: AuthorizedRepo, Path(escrow_id): Path<i32>) -> ApiResult<Escrow> {
+    Ok(Json(repo.release_escrow(escrow_id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/escrows/{escrow_id}/refund",
+    tag = "escrows",
+    params(("escrow_id" = i32, Path, description = "Id of the escrow")),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, body = Escrow),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, body = ErrorBody),
+    )
+)]
+async fn refund_escrow(repo: AuthorizedRepo, Path(escrow_id): Path<i32>) -> ApiResult<Escrow> {
+    Ok(Json(repo.refund_escrow(escrow_id).await?))
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::bigchain::NodeStats;
+
+mod memory;
+
+pub use memory::InMemoryLedger;
+
+/// A BigchainDB key pair, both keys base58 encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPair {
+    pub public_key: String,
+    pub private_key: String,
+}
+
+/// An output of a transaction, as listed by `GET /outputs`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OutputRef {
+    pub transaction_id: String,
+    pub output_index: usize,
+}
+
+/// A transaction in BigchainDB's wire format. Only what is needed to tell
+/// who paid whom how much of which token.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub id: String,
+    pub operation: String,
+    #[serde(default)]
+    pub asset: serde_json::Value,
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+    pub inputs: Vec<Input>,
+    pub outputs: Vec<Output>,
+}
+
+impl Transaction {
+    /// The token moved: the transaction's own id for a CREATE, the id of
+    /// the CREATE it spends from for a TRANSFER.
+    pub fn token(&self) -> Option<&str> {
+        match self.operation.as_str() {
+            "CREATE" => Some(&self.id),
+            _ => self.asset["id"].as_str(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Input {
+    pub owners_before: Vec<String>,
+    /// `None` for the input of a CREATE.
+    pub fulfills: Option<OutputRef>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    pub public_keys: Vec<String>,
+    pub amount: String,
+}
+
+/// A block, as returned by `GET /blocks/{height}`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub height: i64,
+    pub transactions: Vec<Transaction>,
+}
+
+/// Why the ledger failed a call. Every [`Ledger`] returns its failures as
+/// one of these inside `anyhow::Error`, recover it with `downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LedgerError {
+    /// No such transaction or block.
+    NotFound(String),
+    /// The ledger refused the request, e.g. an output spent already.
+    Rejected(String),
+    /// No node could be reached, or every node reached failed.
+    Unavailable(String),
+    /// A write reached a node that failed before confirming it, so it may
+    /// or may not be committed.
+    Unconfirmed(String),
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedgerError::NotFound(msg) => write!(f, "ledger has no {msg}"),
+            LedgerError::Rejected(msg) => write!(f, "ledger rejected the request: {msg}"),
+            LedgerError::Unavailable(msg) => write!(f, "ledger unavailable: {msg}"),
+            LedgerError::Unconfirmed(msg) => write!(f, "ledger write unconfirmed: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for LedgerError {}
+
+/// The ledger holding the tokens, following BigchainDB's rules: a token is
+/// the id of the CREATE minting it, every transaction is signed by the
+/// owners of what it spends, an output is spent at most once, and a
+/// TRANSFER pays out exactly what its inputs hold. [`NodePool`] is the real
+/// thing, [`InMemoryLedger`] a stand-in for tests and local development.
+///
+/// [`NodePool`]: crate::bigchain::NodePool
+#[async_trait]
+pub trait Ledger: Send + Sync {
+    /// A new key pair for a wallet.
+    fn generate_keypair(&self) -> KeyPair;
+
+    /// Mint `amount` units of a new token to `owner`, and wait for the CREATE
+    /// to be committed. Returns its id, which is the token.
+    async fn create(
+        &self,
+        owner: &KeyPair,
+        amount: i32,
+        asset: Option<serde_json::Value>,
+        metadata: Option<serde_json::Value>,
+    ) -> anyhow::Result<String>;
+
+    /// Spend `inputs`, outputs of `token` owned by `owner`, paying every
+    /// `(public_key, amount)` of `outputs`, and wait for the TRANSFER to be
+    /// committed. Returns its id.
+    async fn transfer(
+        &self,
+        owner: &KeyPair,
+        token: &str,
+        inputs: &[OutputRef],
+        outputs: &[(String, i32)],
+        metadata: serde_json::Value,
+    ) -> anyhow::Result<String>;
+
+    /// Outputs paying `public_key`, only the spent or unspent ones when
+    /// `spent` is set.
+    async fn list_outputs(
+        &self,
+        public_key: &str,
+        spent: Option<bool>,
+    ) -> anyhow::Result<Vec<OutputRef>>;
+
+    async fn get_transaction(&self, transaction_id: &str) -> anyhow::Result<Transaction>;
+
+    /// The block at `height`, `None` when the chain is not that high yet.
+    async fn get_block(&self, height: i64) -> anyhow::Result<Option<Block>>;
+
+    /// URL of the websocket stream announcing every committed transaction.
+    async fn stream_url(&self) -> anyhow::Result<String>;
+
+    fn stats(&self) -> Vec<NodeStats>;
+}
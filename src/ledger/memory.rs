@@ -0,0 +1,328 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use async_trait::async_trait;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::rngs::OsRng;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use super::{Block, Input, KeyPair, Ledger, LedgerError, Output, OutputRef, Transaction};
+use crate::bigchain::NodeStats;
+
+#[derive(Default)]
+struct State {
+    /// Committed transactions, one block each: the transaction at index `i`
+    /// is the block at height `i + 1`.
+    transactions: Vec<Transaction>,
+    by_id: HashMap<String, usize>,
+    spent: HashSet<OutputRef>,
+}
+
+/// A ledger living in the process, for tests and local development. It
+/// signs with and checks real ed25519 keys, and rejects what a BigchainDB
+/// node would: spending an output twice or with the wrong key, mixing
+/// tokens and paying out more or less than the inputs hold. Every
+/// transaction is committed in a block of its own. It has no stream, so the
+/// ledger sync can only be driven by hand, see
+/// [`Repo::sync_next_block`](crate::repo::Repo::sync_next_block).
+#[derive(Default)]
+pub struct InMemoryLedger {
+    state: Mutex<State>,
+    requests: AtomicU64,
+}
+
+impl InMemoryLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Committing the same transaction again is rejected, as by a node.
+    fn commit(&self, state: &mut State, tx: Transaction) -> anyhow::Result<String> {
+        if state.by_id.contains_key(&tx.id) {
+            anyhow::bail!(LedgerError::Rejected(format!(
+                "transaction {} is already committed",
+                tx.id
+            )));
+        }
+        for input in tx.inputs.iter() {
+            if let Some(fulfills) = &input.fulfills {
+                state.spent.insert(fulfills.clone());
+            }
+        }
+        let id = tx.id.clone();
+        state.by_id.insert(id.clone(), state.transactions.len());
+        state.transactions.push(tx);
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl Ledger for InMemoryLedger {
+    fn generate_keypair(&self) -> KeyPair {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        KeyPair {
+            public_key: bs58::encode(signing_key.verifying_key().as_bytes()).into_string(),
+            private_key: bs58::encode(signing_key.as_bytes()).into_string(),
+        }
+    }
+
+    async fn create(
+        &self,
+        owner: &KeyPair,
+        amount: i32,
+        asset: Option<serde_json::Value>,
+        metadata: Option<serde_json::Value>,
+    ) -> anyhow::Result<String> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if amount <= 0 {
+            anyhow::bail!(LedgerError::Rejected("amount must be positive".to_string()));
+        }
+
+        let body = json!({
+            "operation": "CREATE",
+            "asset": { "data": asset },
+            "metadata": metadata,
+            "inputs": [{ "owners_before": [&owner.public_key], "fulfills": null }],
+            "outputs": [{ "public_keys": [&owner.public_key], "amount": amount.to_string() }],
+        });
+        let id = sign_and_verify(owner, &body, [owner.public_key.as_str()])?;
+
+        let tx = Transaction {
+            id,
+            operation: "CREATE".to_string(),
+            asset: body["asset"].clone(),
+            metadata: body["metadata"].clone(),
+            inputs: vec![Input {
+                owners_before: vec![owner.public_key.clone()],
+                fulfills: None,
+            }],
+            outputs: vec![Output {
+                public_keys: vec![owner.public_key.clone()],
+                amount: amount.to_string(),
+            }],
+        };
+        self.commit(&mut self.state.lock().unwrap(), tx)
+    }
+
+    async fn transfer(
+        &self,
+        owner: &KeyPair,
+        token: &str,
+        inputs: &[OutputRef],
+        outputs: &[(String, i32)],
+        metadata: serde_json::Value,
+    ) -> anyhow::Result<String> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if inputs.is_empty() || outputs.is_empty() {
+            anyhow::bail!(LedgerError::Rejected(
+                "a transfer needs inputs and outputs".to_string()
+            ));
+        }
+        for (public_key, amount) in outputs {
+            if *amount <= 0 {
+                anyhow::bail!(LedgerError::Rejected("amount must be positive".to_string()));
+            }
+            verifying_key(public_key)?;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let mut owners = Vec::new();
+        let mut input_amount = 0i64;
+        for (i, input) in inputs.iter().enumerate() {
+            if inputs[..i].contains(input) || state.spent.contains(input) {
+                anyhow::bail!(LedgerError::Rejected(format!(
+                    "output {} of {} is already spent",
+                    input.output_index, input.transaction_id
+                )));
+            }
+            let tx = state
+                .by_id
+                .get(&input.transaction_id)
+                .map(|&i| &state.transactions[i])
+                .ok_or_else(|| {
+                    anyhow::anyhow!(LedgerError::NotFound(format!(
+                        "transaction {}",
+                        input.transaction_id
+                    )))
+                })?;
+            let output = tx.outputs.get(input.output_index).ok_or_else(|| {
+                anyhow::anyhow!(LedgerError::Rejected(format!(
+                    "transaction {} has no output {}",
+                    input.transaction_id, input.output_index
+                )))
+            })?;
+            if tx.token() != Some(token) {
+                anyhow::bail!(LedgerError::Rejected(format!(
+                    "output {} of {} does not hold token {token}",
+                    input.output_index, input.transaction_id
+                )));
+            }
+            owners.push(output.public_keys.clone());
+            input_amount += output.amount.parse::<i64>()?;
+        }
+        let output_amount: i64 = outputs.iter().map(|(_, amount)| *amount as i64).sum();
+        if input_amount != output_amount {
+            anyhow::bail!(LedgerError::Rejected(format!(
+                "inputs hold {input_amount} but outputs pay {output_amount}"
+            )));
+        }
+
+        let body = json!({
+            "operation": "TRANSFER",
+            "asset": { "id": token },
+            "metadata": metadata,
+            "inputs": inputs.iter().zip(owners.iter()).map(|(input, owners_before)| json!({
+                "owners_before": owners_before,
+                "fulfills": {
+                    "transaction_id": &input.transaction_id,
+                    "output_index": input.output_index,
+                },
+            })).collect::<Vec<_>>(),
+            "outputs": outputs.iter().map(|(public_key, amount)| json!({
+                "public_keys": [public_key],
+                "amount": amount.to_string(),
+            })).collect::<Vec<_>>(),
+        });
+        let id = sign_and_verify(owner, &body, owners.iter().flatten().map(String::as_str))?;
+
+        let tx = Transaction {
+            id,
+            operation: "TRANSFER".to_string(),
+            asset: body["asset"].clone(),
+            metadata: body["metadata"].clone(),
+            inputs: inputs
+                .iter()
+                .zip(owners)
+                .map(|(input, owners_before)| Input {
+                    owners_before,
+                    fulfills: Some(input.clone()),
+                })
+                .collect(),
+            outputs: outputs
+                .iter()
+                .map(|(public_key, amount)| Output {
+                    public_keys: vec![public_key.clone()],
+                    amount: amount.to_string(),
+                })
+                .collect(),
+        };
+        self.commit(&mut state, tx)
+    }
+
+    async fn list_outputs(
+        &self,
+        public_key: &str,
+        spent: Option<bool>,
+    ) -> anyhow::Result<Vec<OutputRef>> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let state = self.state.lock().unwrap();
+        let mut list = Vec::new();
+        for tx in state.transactions.iter() {
+            for (output_index, output) in tx.outputs.iter().enumerate() {
+                let output_ref = OutputRef {
+                    transaction_id: tx.id.clone(),
+                    output_index,
+                };
+                if output.public_keys.iter().any(|key| key == public_key)
+                    && spent.is_none_or(|spent| spent == state.spent.contains(&output_ref))
+                {
+                    list.push(output_ref);
+                }
+            }
+        }
+        Ok(list)
+    }
+
+    async fn get_transaction(&self, transaction_id: &str) -> anyhow::Result<Transaction> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let state = self.state.lock().unwrap();
+        state
+            .by_id
+            .get(transaction_id)
+            .map(|&i| state.transactions[i].clone())
+            .ok_or_else(|| {
+                anyhow::anyhow!(LedgerError::NotFound(format!(
+                    "transaction {transaction_id}"
+                )))
+            })
+    }
+
+    async fn get_block(&self, height: i64) -> anyhow::Result<Option<Block>> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let state = self.state.lock().unwrap();
+        let tx = usize::try_from(height - 1)
+            .ok()
+            .and_then(|i| state.transactions.get(i));
+        Ok(tx.map(|tx| Block {
+            height,
+            transactions: vec![tx.clone()],
+        }))
+    }
+
+    async fn stream_url(&self) -> anyhow::Result<String> {
+        anyhow::bail!(LedgerError::Unavailable(
+            "the in-memory ledger has no stream".to_string()
+        ))
+    }
+
+    fn stats(&self) -> Vec<NodeStats> {
+        vec![NodeStats {
+            url: "memory".to_string(),
+            healthy: true,
+            requests: self.requests.load(Ordering::Relaxed),
+            failures: 0,
+        }]
+    }
+}
+
+fn verifying_key(public_key: &str) -> anyhow::Result<VerifyingKey> {
+    let bytes = bs58::decode(public_key)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| {
+            anyhow::anyhow!(LedgerError::Rejected(format!(
+                "invalid public key {public_key}"
+            )))
+        })?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| {
+        anyhow::anyhow!(LedgerError::Rejected(format!(
+            "invalid public key {public_key}"
+        )))
+    })
+}
+
+/// Sign `body` with `keypair` and check the signature against every owner
+/// whose output is spent, as a node checks the fulfillment of every input.
+/// Returns the transaction id, the SHA-256 of the body.
+fn sign_and_verify<'a>(
+    keypair: &KeyPair,
+    body: &serde_json::Value,
+    owners: impl IntoIterator<Item = &'a str>,
+) -> anyhow::Result<String> {
+    let seed = bs58::decode(&keypair.private_key)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| anyhow::anyhow!(LedgerError::Rejected("invalid private key".to_string())))?;
+    let message = serde_json::to_vec(body)?;
+    let signature: Signature = SigningKey::from_bytes(&seed).sign(&message);
+
+    for owner in owners {
+        verifying_key(owner)?
+            .verify(&message, &signature)
+            .map_err(|_| {
+                anyhow::anyhow!(LedgerError::Rejected(format!(
+                    "invalid signature of {owner}"
+                )))
+            })?;
+    }
+
+    Ok(hex::encode(Sha256::digest(&message)))
+}
@@ -1,4 +1,16 @@
+pub mod auth;
+pub mod bigchain;
+pub mod cache;
+pub mod config;
+pub mod db;
 pub mod entity;
+pub mod events;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod http;
+pub mod ledger;
 pub mod migrator;
 pub mod repo;
 
@@ -7,12 +19,24 @@ pub use sea_orm::*;
 // use sea_orm::{Database, DatabaseConnection, DbErr};
 use sea_orm_migration::MigratorTrait;
 
+pub use crate::db::{DbConfig, MigrationMode};
 use crate::migrator::Migrator;
 
 pub async fn connect(url: &str) -> Result<DatabaseConnection, DbErr> {
-    let db = Database::connect(url).await?;
+    connect_with(&DbConfig::new(url)).await
+}
+
+/// Like [`connect`], with pool, timeout, logging and schema settings taken
+/// from `config`, handling pending migrations according to
+/// `config.migrations`.
+pub async fn connect_with(config: &DbConfig) -> Result<DatabaseConnection, DbErr> {
+    let db = config.connect().await?;
 
-    Migrator::up(&db, None).await?;
+    match config.migrations {
+        MigrationMode::Apply => Migrator::up(&db, None).await?,
+        MigrationMode::Verify => migrator::verify(&db).await?,
+        MigrationMode::Skip => {}
+    }
 
     // let schema_manager = SchemaManager::new(&db);
     // assert!(schema_manager.has_table("edges_to_wallets").await?);
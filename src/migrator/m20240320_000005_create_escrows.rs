@@ -0,0 +1,124 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_query::extension::postgres::Type;
+
+use super::m20240318_000002_create_tokens::Tokens;
+use super::m20240318_000003_create_wallets::Wallets;
+
+#[derive(Iden)]
+pub enum EscrowState {
+    #[iden = "escrow_state"]
+    Type,
+    Open,
+    Released,
+    Refunded,
+}
+
+#[derive(Iden)]
+pub enum Escrows {
+    Table,
+    Id,
+    EdgeId,
+    EscrowWalletId,
+    TokenId,
+    Amount,
+    State,
+    Deadline,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240320_000005_create_escrows.rs"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(EscrowState::Type)
+                    .values([
+                        EscrowState::Open,
+                        EscrowState::Released,
+                        EscrowState::Refunded,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(Escrows::Table)
+                    .col(
+                        ColumnDef::new(Escrows::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Escrows::EdgeId).integer().not_null())
+                    .col(ColumnDef::new(Escrows::EscrowWalletId).integer().not_null())
+                    .col(ColumnDef::new(Escrows::TokenId).integer().not_null())
+                    .col(ColumnDef::new(Escrows::Amount).integer().not_null())
+                    .col(
+                        ColumnDef::new(Escrows::State)
+                            .enumeration(
+                                EscrowState::Type,
+                                [
+                                    EscrowState::Open,
+                                    EscrowState::Released,
+                                    EscrowState::Refunded,
+                                ],
+                            )
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Escrows::Deadline)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Escrows::Table, Escrows::EscrowWalletId)
+                            .to(Wallets::Table, Wallets::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Escrows::Table, Escrows::TokenId)
+                            .to(Tokens::Table, Tokens::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_escrows_state_deadline")
+                    .table(Escrows::Table)
+                    .col(Escrows::State)
+                    .col(Escrows::Deadline)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Escrows::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(EscrowState::Type).to_owned())
+            .await
+    }
+}
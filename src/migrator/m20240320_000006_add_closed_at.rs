@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240318_000001_create_edges_to_wallets::EdgesToWallets;
+use super::m20240318_000003_create_wallets::Wallets;
+
+#[derive(Iden)]
+enum ClosedAt {
+    ClosedAt,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240320_000006_add_closed_at.rs"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EdgesToWallets::Table)
+                    .add_column(
+                        ColumnDef::new(ClosedAt::ClosedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Wallets::Table)
+                    .add_column(
+                        ColumnDef::new(ClosedAt::ClosedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Wallets::Table)
+                    .drop_column(ClosedAt::ClosedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EdgesToWallets::Table)
+                    .drop_column(ClosedAt::ClosedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
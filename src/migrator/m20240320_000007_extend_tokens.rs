@@ -0,0 +1,105 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_query::extension::postgres::Type;
+
+use super::m20240318_000002_create_tokens::Tokens;
+use super::m20240318_000003_create_wallets::Wallets;
+
+#[derive(Iden)]
+pub enum TokenKind {
+    #[iden = "token_kind"]
+    Type,
+    Fungible,
+    NonFungible,
+}
+
+#[derive(Iden)]
+enum TokensExt {
+    Asset,
+    Metadata,
+    Supply,
+    Kind,
+    CreatorWalletId,
+    CreatedAt,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240320_000007_extend_tokens.rs"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(TokenKind::Type)
+                    .values([TokenKind::Fungible, TokenKind::NonFungible])
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing rows are left NULL here and filled in by
+        // `Repo::backfill_tokens` (`bc_orm backfill`), which needs the
+        // BigchainDB node.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tokens::Table)
+                    .add_column(ColumnDef::new(TokensExt::Asset).json_binary().null())
+                    .add_column(ColumnDef::new(TokensExt::Metadata).json_binary().null())
+                    .add_column(ColumnDef::new(TokensExt::Supply).integer().null())
+                    .add_column(
+                        ColumnDef::new(TokensExt::Kind)
+                            .enumeration(
+                                TokenKind::Type,
+                                [TokenKind::Fungible, TokenKind::NonFungible],
+                            )
+                            .null(),
+                    )
+                    .add_column(ColumnDef::new(TokensExt::CreatorWalletId).integer().null())
+                    .add_column(
+                        ColumnDef::new(TokensExt::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_tokens_creator_wallet_id")
+                            .from_tbl(Tokens::Table)
+                            .from_col(TokensExt::CreatorWalletId)
+                            .to_tbl(Wallets::Table)
+                            .to_col(Wallets::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tokens::Table)
+                    .drop_foreign_key(Alias::new("fk_tokens_creator_wallet_id"))
+                    .drop_column(TokensExt::Asset)
+                    .drop_column(TokensExt::Metadata)
+                    .drop_column(TokensExt::Supply)
+                    .drop_column(TokensExt::Kind)
+                    .drop_column(TokensExt::CreatorWalletId)
+                    .drop_column(TokensExt::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(TokenKind::Type).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,91 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240318_000001_create_edges_to_wallets::EdgesToWallets;
+use super::m20240318_000002_create_tokens::Tokens;
+use super::m20240318_000003_create_wallets::Wallets;
+use super::m20240318_000004_create_wallets_to_tokens::WalletsToTokens;
+use super::m20240320_000005_create_escrows::Escrows;
+
+#[derive(Iden)]
+enum Timestamps {
+    CreatedAt,
+    UpdatedAt,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240320_000008_add_timestamps.rs"
+    }
+}
+
+fn timestamp(col: Timestamps) -> ColumnDef {
+    ColumnDef::new(col)
+        .timestamp_with_time_zone()
+        .not_null()
+        .default(Expr::current_timestamp())
+        .to_owned()
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in [
+            EdgesToWallets::Table.into_iden(),
+            Wallets::Table.into_iden(),
+            WalletsToTokens::Table.into_iden(),
+            Escrows::Table.into_iden(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .add_column(&mut timestamp(Timestamps::CreatedAt))
+                        .add_column(&mut timestamp(Timestamps::UpdatedAt))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // tokens already has created_at since m20240320_000007
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tokens::Table)
+                    .add_column(&mut timestamp(Timestamps::UpdatedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tokens::Table)
+                    .drop_column(Timestamps::UpdatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        for table in [
+            EdgesToWallets::Table.into_iden(),
+            Wallets::Table.into_iden(),
+            WalletsToTokens::Table.into_iden(),
+            Escrows::Table.into_iden(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .drop_column(Timestamps::CreatedAt)
+                        .drop_column(Timestamps::UpdatedAt)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+pub enum ApiClients {
+    Table,
+    Id,
+    Name,
+    KeyHash,
+    Scopes,
+    EdgeIds,
+    RevokedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240321_000009_create_api_clients.rs"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(ApiClients::Table)
+                    .col(
+                        ColumnDef::new(ApiClients::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ApiClients::Name)
+                            .text()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ApiClients::KeyHash)
+                            .text()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ApiClients::Scopes)
+                            .array(ColumnType::Text)
+                            .not_null(),
+                    )
+                    // NULL grants every edge
+                    .col(
+                        ColumnDef::new(ApiClients::EdgeIds)
+                            .array(ColumnType::Integer)
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ApiClients::RevokedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ApiClients::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(ApiClients::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiClients::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,88 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240318_000001_create_edges_to_wallets::EdgesToWallets;
+use super::m20240318_000002_create_tokens::Tokens;
+use super::m20240318_000003_create_wallets::Wallets;
+use super::m20240318_000004_create_wallets_to_tokens::WalletsToTokens;
+use super::m20240320_000005_create_escrows::Escrows;
+use super::m20240321_000009_create_api_clients::ApiClients;
+
+#[derive(Iden)]
+enum TenantId {
+    TenantId,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240322_000010_add_tenant_id.rs"
+    }
+}
+
+/// Tables owned by a tenant, with the name of their tenant index. Existing
+/// rows go to the default tenant 0.
+fn tables() -> [(DynIden, &'static str); 6] {
+    [
+        (
+            EdgesToWallets::Table.into_iden(),
+            "idx_edges_to_wallets_tenant_id",
+        ),
+        (Wallets::Table.into_iden(), "idx_wallets_tenant_id"),
+        (Tokens::Table.into_iden(), "idx_tokens_tenant_id"),
+        (
+            WalletsToTokens::Table.into_iden(),
+            "idx_wallets_to_tokens_tenant_id",
+        ),
+        (Escrows::Table.into_iden(), "idx_escrows_tenant_id"),
+        (ApiClients::Table.into_iden(), "idx_api_clients_tenant_id"),
+    ]
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (table, index) in tables() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table.clone())
+                        .add_column(
+                            ColumnDef::new(TenantId::TenantId)
+                                .integer()
+                                .not_null()
+                                .default(0),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+
+            manager
+                .create_index(
+                    Index::create()
+                        .name(index)
+                        .table(table)
+                        .col(TenantId::TenantId)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (table, _) in tables() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .drop_column(TenantId::TenantId)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240318_000002_create_tokens::Tokens;
+use super::m20240318_000003_create_wallets::Wallets;
+
+#[derive(Iden)]
+pub enum LedgerTransactions {
+    Table,
+    Id,
+    TransactionId,
+    Operation,
+    Token,
+    Height,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+pub enum BalanceChanges {
+    Table,
+    Id,
+    TenantId,
+    LedgerTransactionId,
+    WalletId,
+    TokenId,
+    Amount,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+pub enum LedgerCursors {
+    Table,
+    Name,
+    Height,
+    UpdatedAt,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240325_000013_create_ledger_sync.rs"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(LedgerTransactions::Table)
+                    .col(
+                        ColumnDef::new(LedgerTransactions::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    // a ledger transaction is applied to the balances once
+                    .col(
+                        ColumnDef::new(LedgerTransactions::TransactionId)
+                            .text()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(LedgerTransactions::Operation)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(LedgerTransactions::Token).text().not_null())
+                    // NULL for transactions posted by the Repo itself
+                    .col(
+                        ColumnDef::new(LedgerTransactions::Height)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(LedgerTransactions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(BalanceChanges::Table)
+                    .col(
+                        ColumnDef::new(BalanceChanges::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(BalanceChanges::TenantId)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(BalanceChanges::LedgerTransactionId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BalanceChanges::WalletId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(BalanceChanges::TokenId).integer().not_null())
+                    .col(ColumnDef::new(BalanceChanges::Amount).integer().not_null())
+                    .col(
+                        ColumnDef::new(BalanceChanges::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(BalanceChanges::Table, BalanceChanges::LedgerTransactionId)
+                            .to(LedgerTransactions::Table, LedgerTransactions::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(BalanceChanges::Table, BalanceChanges::WalletId)
+                            .to(Wallets::Table, Wallets::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(BalanceChanges::Table, BalanceChanges::TokenId)
+                            .to(Tokens::Table, Tokens::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_balance_changes_wallet_id")
+                    .table(BalanceChanges::Table)
+                    .col(BalanceChanges::WalletId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .if_not_exists()
+                    .table(LedgerCursors::Table)
+                    .col(
+                        ColumnDef::new(LedgerCursors::Name)
+                            .text()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    // last block applied
+                    .col(
+                        ColumnDef::new(LedgerCursors::Height)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LedgerCursors::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LedgerCursors::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(BalanceChanges::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(LedgerTransactions::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240318_000001_create_edges_to_wallets::EdgesToWallets;
+
+#[derive(Iden)]
+enum TenantId {
+    TenantId,
+}
+
+const INDEX: &str = "idx_edges_to_wallets_tenant_id_edge_id";
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240326_000014_unique_edge_id.rs"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    /// Fails while a tenant has an `edge_id` provisioned more than once,
+    /// those duplicates have to be deprovisioned and removed first.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_index(
+                Index::create()
+                    .name(INDEX)
+                    .table(EdgesToWallets::Table)
+                    .col(TenantId::TenantId)
+                    .col(EdgesToWallets::EdgeId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(INDEX)
+                    .table(EdgesToWallets::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_query::extension::postgres::Type;
+
+use super::m20240320_000005_create_escrows::EscrowState;
+
+#[derive(Iden)]
+enum SettlingState {
+    Releasing,
+    Refunding,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m_20240327_000015_add_settling_escrow_states.rs"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for state in [SettlingState::Releasing, SettlingState::Refunding] {
+            manager
+                .alter_type(
+                    Type::alter()
+                        .name(EscrowState::Type)
+                        .add_value(state)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Postgres cannot drop enum values, so the type is recreated without
+    /// them. Escrows left half way are reopened: settle them again after
+    /// checking their wallets on the ledger.
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE escrows SET state = 'open' WHERE state IN ('releasing', 'refunding');
+                ALTER TYPE escrow_state RENAME TO escrow_state_settling;
+                CREATE TYPE escrow_state AS ENUM ('open', 'released', 'refunded');
+                ALTER TABLE escrows
+                    ALTER COLUMN state TYPE escrow_state USING state::text::escrow_state;
+                DROP TYPE escrow_state_settling;",
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -2,8 +2,21 @@ mod m20240318_000001_create_edges_to_wallets;
 mod m20240318_000002_create_tokens;
 mod m20240318_000003_create_wallets;
 mod m20240318_000004_create_wallets_to_tokens;
+mod m20240320_000005_create_escrows;
+mod m20240320_000006_add_closed_at;
+mod m20240320_000007_extend_tokens;
+mod m20240320_000008_add_timestamps;
+mod m20240321_000009_create_api_clients;
+mod m20240322_000010_add_tenant_id;
+mod m20240323_000011_create_outbox_events;
+mod m20240324_000012_create_webhooks;
+mod m20240325_000013_create_ledger_sync;
+mod m20240326_000014_unique_edge_id;
+mod m20240327_000015_add_settling_escrow_states;
 
-use sea_orm_migration::prelude::*;
+use sea_orm::DatabaseConnection;
+use sea_orm_migration::{prelude::*, MigrationStatus};
+use serde::Serialize;
 
 pub struct Migrator;
 
@@ -15,6 +28,90 @@ impl MigratorTrait for Migrator {
             Box::new(m20240318_000003_create_wallets::Migration),
             Box::new(m20240318_000001_create_edges_to_wallets::Migration),
             Box::new(m20240318_000004_create_wallets_to_tokens::Migration),
+            Box::new(m20240320_000005_create_escrows::Migration),
+            Box::new(m20240320_000006_add_closed_at::Migration),
+            Box::new(m20240320_000007_extend_tokens::Migration),
+            Box::new(m20240320_000008_add_timestamps::Migration),
+            Box::new(m20240321_000009_create_api_clients::Migration),
+            Box::new(m20240322_000010_add_tenant_id::Migration),
+            Box::new(m20240323_000011_create_outbox_events::Migration),
+            Box::new(m20240324_000012_create_webhooks::Migration),
+            Box::new(m20240325_000013_create_ledger_sync::Migration),
+            Box::new(m20240326_000014_unique_edge_id::Migration),
+            Box::new(m20240327_000015_add_settling_escrow_states::Migration),
         ]
     }
 }
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct MigrationState {
+    pub name: String,
+    pub applied: bool,
+}
+
+/// Every known migration in order, with whether it is applied.
+pub async fn status<C: ConnectionTrait>(db: &C) -> Result<Vec<MigrationState>, DbErr> {
+    Ok(Migrator::get_migration_with_status(db)
+        .await?
+        .into_iter()
+        .map(|migration| MigrationState {
+            name: migration.name().to_string(),
+            applied: migration.status() == MigrationStatus::Applied,
+        })
+        .collect())
+}
+
+/// Fail if any migration is pending.
+pub async fn verify<C: ConnectionTrait>(db: &C) -> Result<(), DbErr> {
+    let pending = Migrator::get_pending_migrations(db).await?;
+    if !pending.is_empty() {
+        let names = pending
+            .iter()
+            .map(|migration| migration.name())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(DbErr::Custom(format!("pending migrations: {names}")));
+    }
+    Ok(())
+}
+
+#[derive(clap::Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum MigrateCommand {
+    /// Apply pending migrations
+    Up {
+        /// Number of migrations to apply, all when unset
+        #[arg(long, short)]
+        steps: Option<u32>,
+    },
+    /// Roll back applied migrations
+    Down {
+        /// Number of migrations to roll back
+        #[arg(long, short, default_value_t = 1)]
+        steps: u32,
+    },
+    /// List migrations and whether they are applied
+    Status,
+    /// Drop every table and apply all migrations
+    Fresh,
+    /// Roll back every migration and apply them again
+    Refresh,
+}
+
+impl MigrateCommand {
+    /// Whether the command loses data.
+    pub fn is_destructive(&self) -> bool {
+        matches!(self, Self::Down { .. } | Self::Fresh | Self::Refresh)
+    }
+
+    /// Run the command and return the resulting status.
+    pub async fn run(&self, db: &DatabaseConnection) -> Result<Vec<MigrationState>, DbErr> {
+        match self {
+            Self::Up { steps } => Migrator::up(db, *steps).await?,
+            Self::Down { steps } => Migrator::down(db, Some(*steps)).await?,
+            Self::Status => {}
+            Self::Fresh => Migrator::fresh(db).await?,
+            Self::Refresh => Migrator::refresh(db).await?,
+        }
+        status(db).await
+    }
+}
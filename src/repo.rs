@@ -1,56 +1,146 @@
-use std::sync::Arc;
-
-use bigchaindb::{
-    connection::Connection,
-    ed25519_keypair,
-    transaction::{Operation, Transaction, UnspentOutput},
-};
+use std::{collections::HashMap, sync::Arc};
 
 use sea_orm::{
-    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DatabaseTransaction,
-    DbErr, EntityTrait, FromQueryResult, IntoActiveModel, JoinType, QueryFilter, QuerySelect,
-    RelationTrait, TransactionTrait, TryIntoModel,
+    prelude::DateTimeWithTimeZone,
+    sea_query::{Alias, Condition, Expr, Query, SelectStatement},
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbErr, FromQueryResult,
+    JoinType, Order, PaginatorTrait, QueryFilter, SqlErr, TransactionTrait, TryIntoModel,
 };
 use serde::{Deserialize, Serialize};
 use serde_json;
+use utoipa::ToSchema;
+
+use crate::{
+    bigchain::NodeStats,
+    cache::{CacheStats, EdgeCache},
+    entity::{prelude::*, sea_orm_active_enums::TokenKind, *},
+    events::Event,
+    ledger::{KeyPair, Ledger, OutputRef},
+};
 
-use crate::entity::{prelude::*, *};
+mod backfill;
+mod batch;
+mod client;
+mod deprovision;
+mod error;
+mod escrow;
+mod list;
+mod outbox;
+mod reconcile;
+mod sync;
+mod tenant;
+mod timestamps;
+mod wallet;
+mod webhook;
+
+pub use batch::{BatchTransfer, Payout};
+pub use client::{ApiClient, IssuedApiClient, NewApiClient, Scope};
+pub use error::RepoError;
+use error::{transaction_error, TxError};
+pub use escrow::OpenEscrow;
+pub use list::{
+    EdgeWalletPage, ListEdges, ListTokens, ListWallets, Page, SortBy, SortOrder, TokenPage,
+    WalletPage,
+};
+pub use reconcile::Discrepancy;
+pub use sync::BalanceChange;
+pub use tenant::{TenantScoped, DEFAULT_TENANT_ID};
+pub use webhook::{
+    sign_webhook, IssuedWebhook, NewWebhook, RetryPolicy, WebhookSink, DELIVERY_HEADER,
+    DELIVERY_LEASE, EVENT_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, ToSchema, Debug)]
 pub struct ProvisionWallet {
     pub edge_id: i32,
+    /// Asset data of the edge's NFT.
+    #[schema(value_type = Object)]
     pub asset: serde_json::Value,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, ToSchema, Debug)]
 pub struct TransferToken {
     pub edge_id: i32,
 }
 
-#[derive(FromQueryResult, Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema, Clone)]
+pub struct Balance {
+    #[serde(skip_serializing)]
+    pub token_id: i32,
+
+    pub token: String,
+    pub volume: i32,
+}
+
+#[derive(Debug, Serialize, ToSchema, Clone)]
 pub struct Wallet {
     #[serde(skip_serializing)]
     pub wallet_id: i32,
 
     pub public_key: String,
+    /// Signs the wallet's ledger transfers, never sent to API callers.
+    #[serde(skip_serializing)]
     pub private_key: String,
+    pub balances: Vec<Balance>,
+
+    #[serde(skip_serializing)]
+    pub closed_at: Option<DateTimeWithTimeZone>,
+}
+
+impl Wallet {
+    /// Volume of `token` held by the wallet, zero when it holds none.
+    pub fn volume(&self, token: &str) -> i32 {
+        self.balances
+            .iter()
+            .filter(|balance| balance.token == token)
+            .map(|balance| balance.volume)
+            .sum()
+    }
+
+    fn keypair(&self) -> KeyPair {
+        KeyPair {
+            public_key: self.public_key.clone(),
+            private_key: self.private_key.clone(),
+        }
+    }
+}
+
+/// An unspent output on the ledger, with the token and amount it holds.
+pub(crate) struct UnspentOutput {
+    pub output: OutputRef,
     pub token: String,
-    pub volume: i32,
+    pub amount: i32,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, ToSchema, Debug, Clone)]
 pub struct EdgeWallet {
     pub edge_id: i32,
     pub src_wallet: Wallet,
     pub dst_wallet: Wallet,
+    /// The edge's FT, minted by `src_wallet`.
     pub token: String,
+    #[serde(skip_serializing)]
+    pub token_id: i32,
     pub nft: String,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub closed_at: Option<DateTimeWithTimeZone>,
 }
 
 pub struct Repo {
     pub db: DatabaseConnection,
-    pub bigchain_url: String,
-    pub init_amount: i32,
+    pub ledger: Arc<dyn Ledger>,
+    /// Units minted for every edge's FT, all credited to its `src_wallet`.
+    pub ft_supply: i32,
+    /// Metadata attached to the CREATE transaction of every minted token.
+    pub mint_metadata: serde_json::Value,
+    /// Receives the balances swept by [`Repo::deprovision_edge`], which
+    /// fails without one.
+    pub treasury_public_key: Option<String>,
+    pub cache: Option<Arc<EdgeCache>>,
+    /// Tenant whose rows this repo reads and writes, see [`Repo::for_tenant`].
+    pub tenant_id: i32,
 }
 
 impl Repo {
@@ -58,22 +148,35 @@ impl Repo {
         self: Arc<Self>,
         data: ProvisionWallet,
     ) -> anyhow::Result<EdgeWallet> {
+        // checked before minting, the unique index settles races
+        let provisioned = self
+            .find::<EdgesToWallets>()
+            .filter(edges_to_wallets::Column::EdgeId.eq(data.edge_id))
+            .count(&self.db)
+            .await?;
+        if provisioned > 0 {
+            anyhow::bail!(RepoError::Conflict(
+                "edge_id is already provisioned".to_string()
+            ));
+        }
+
         let _self = self.clone();
         _self
             .db
-            .transaction::<_, (), DbErr>(|tx| {
+            .transaction::<_, (), TxError>(|tx| {
                 Box::pin(async move {
                     let src_wallet = self.create_wallet(tx).await?;
                     let dst_wallet = self.create_wallet(tx).await?;
                     let nft_wallet = self.create_wallet(tx).await?;
 
-                    let metadata = serde_json::json!({ "co": "devr" });
+                    let metadata = self.mint_metadata.clone();
 
                     // create FT
                     let token = self
                         .create_token(
                             &src_wallet,
-                            100,
+                            self.ft_supply,
+                            TokenKind::Fungible,
                             Some(data.asset),
                             Some(metadata.clone()),
                             tx,
@@ -86,13 +189,20 @@ impl Repo {
                         "token": "NFT",
                     });
                     let nft = self
-                        .create_token(&nft_wallet, 1, Some(nft_asset), Some(metadata), tx)
+                        .create_token(
+                            &nft_wallet,
+                            1,
+                            TokenKind::NonFungible,
+                            Some(nft_asset),
+                            Some(metadata),
+                            tx,
+                        )
                         .await
                         .map_err(|_| DbErr::Custom("create NFT error".to_string()))?;
 
                     // create wallet_to_token
                     let _ = self
-                        .create_wallet_to_token(src_wallet.id, token.id, self.init_amount, tx)
+                        .create_wallet_to_token(src_wallet.id, token.id, self.ft_supply, tx)
                         .await
                         .map_err(|_| {
                             DbErr::Custom("create src wallet_to_token error".to_string())
@@ -110,6 +220,26 @@ impl Repo {
                             DbErr::Custom("create nft wallet_to_token error".to_string())
                         })?;
 
+                    // so that the ledger sync does not credit the mints again
+                    for (minted, wallet_id, amount) in [
+                        (&token, src_wallet.id, self.ft_supply),
+                        (&nft, nft_wallet.id, 1),
+                    ] {
+                        if let Some(ledger_transaction_id) = self
+                            .claim_transaction(&minted.token, "CREATE", &minted.token, None, tx)
+                            .await?
+                        {
+                            self.record_balance_change(
+                                ledger_transaction_id,
+                                wallet_id,
+                                minted.id,
+                                amount,
+                                tx,
+                            )
+                            .await?;
+                        }
+                    }
+
                     let _ = self
                         .create_edge_to_wallet(
                             data.edge_id,
@@ -119,12 +249,31 @@ impl Repo {
                             tx,
                         )
                         .await
-                        .map_err(|_| DbErr::Custom("create edge_to_wallet error".to_string()))?;
+                        .map_err(|e| match e.sql_err() {
+                            Some(SqlErr::UniqueConstraintViolation(_)) => TxError::from(
+                                RepoError::Conflict("edge_id is already provisioned".to_string()),
+                            ),
+                            _ => e.into(),
+                        })?;
+
+                    self.record_event(
+                        Event::EdgeProvisioned {
+                            edge_id: data.edge_id,
+                            token: token.token,
+                            nft: nft.token,
+                            src_public_key: src_wallet.public_key,
+                            dst_public_key: dst_wallet.public_key,
+                        },
+                        tx,
+                    )
+                    .await?;
 
                     Ok(())
                 })
             })
-            .await?;
+            .await
+            .map_err(transaction_error)?;
+        _self.invalidate_edge(data.edge_id).await;
 
         _self.get_edge_wallet(data.edge_id).await
     }
@@ -134,8 +283,11 @@ impl Repo {
         data: TransferToken,
     ) -> anyhow::Result<EdgeWallet> {
         let edge_wallet = self.get_edge_wallet(data.edge_id).await?;
+        if edge_wallet.closed_at.is_some() {
+            anyhow::bail!(RepoError::Conflict("edge_id is closed".to_string()));
+        }
 
-        let _ = self
+        let transaction_id = self
             .bigchain_transfer_token(
                 &edge_wallet.src_wallet,
                 &edge_wallet.dst_wallet,
@@ -147,42 +299,48 @@ impl Repo {
         let _self = self.clone();
         _self
             .db
-            .transaction::<_, (), DbErr>(|tx| {
+            .transaction::<_, (), TxError>(|tx| {
                 Box::pin(async move {
-                    let mut src_wallet = WalletsToTokens::find()
-                        .filter(
-                            wallets_to_tokens::Column::WalletId
-                                .eq(edge_wallet.src_wallet.wallet_id),
+                    // the ledger sync may have applied it already
+                    if let Some(ledger_transaction_id) = self
+                        .claim_transaction(
+                            &transaction_id,
+                            "TRANSFER",
+                            &edge_wallet.token,
+                            None,
+                            tx,
                         )
-                        .one(tx)
                         .await?
-                        .ok_or_else(|| DbErr::Custom("find src_wallet error".to_string()))?
-                        .into_active_model();
-
-                    let src_wallet_vol = src_wallet.volume.clone().unwrap();
-                    if src_wallet_vol > 0 {
-                        src_wallet.volume = Set(src_wallet_vol - 1);
-                    }
-                    let _ = src_wallet.update(tx).await?;
-
-                    let mut dst_wallet = WalletsToTokens::find()
-                        .filter(
-                            wallets_to_tokens::Column::WalletId
-                                .eq(edge_wallet.dst_wallet.wallet_id),
+                    {
+                        self.move_volume(
+                            edge_wallet.src_wallet.wallet_id,
+                            edge_wallet.dst_wallet.wallet_id,
+                            edge_wallet.token_id,
+                            1,
+                            ledger_transaction_id,
+                            tx,
                         )
-                        .one(tx)
-                        .await?
-                        .ok_or_else(|| DbErr::Custom("find src_wallet error".to_string()))?
-                        .into_active_model();
+                        .await?;
+                    }
 
-                    let dst_wallet_vol = dst_wallet.volume.clone().unwrap();
-                    dst_wallet.volume = Set(dst_wallet_vol + 1);
-                    let _ = dst_wallet.update(tx).await?;
+                    self.record_event(
+                        Event::TokensTransferred {
+                            edge_id: Some(edge_wallet.edge_id),
+                            token: edge_wallet.token,
+                            from_public_key: edge_wallet.src_wallet.public_key,
+                            to_public_key: edge_wallet.dst_wallet.public_key,
+                            amount: 1,
+                        },
+                        tx,
+                    )
+                    .await?;
 
                     Ok(())
                 })
             })
-            .await?;
+            .await
+            .map_err(transaction_error)?;
+        _self.invalidate_edge(data.edge_id).await;
 
         _self.get_edge_wallet(data.edge_id).await
     }
@@ -193,131 +351,197 @@ impl Repo {
         receiver: &Wallet,
         token: &str,
         transfer_amount: i32,
-    ) -> anyhow::Result<()> {
-        let mut conn = Connection::new(vec![&self.bigchain_url]);
+    ) -> anyhow::Result<String> {
+        self.bigchain_transfer(
+            sender,
+            token,
+            &[(&receiver.public_key, transfer_amount)],
+            serde_json::json!({
+                "transfer_to": &receiver.public_key,
+                "transfer_amount": transfer_amount,
+            }),
+        )
+        .await
+    }
 
-        let list_outputs = conn.list_outputs(&sender.public_key, Some(false)).await?;
+    /// Spend all of the sender's unspent outputs of `token` in one TRANSFER
+    /// paying every `(public_key, amount)` recipient, returning the change to
+    /// the sender. Returns the id of the committed transaction.
+    async fn bigchain_transfer(
+        &self,
+        sender: &Wallet,
+        token: &str,
+        recipients: &[(&str, i32)],
+        metadata: serde_json::Value,
+    ) -> anyhow::Result<String> {
+        let unspent_outputs = self
+            .unspent_outputs(&sender.public_key)
+            .await?
+            .into_iter()
+            .filter(|unspent_output| unspent_output.token == token)
+            .collect::<Vec<_>>();
+        if unspent_outputs.is_empty() {
+            anyhow::bail!(RepoError::InsufficientFunds(format!(
+                "no unspent output of token {token}"
+            )));
+        }
 
-        // find unspent_output of sender's pubkey and token
-        let mut unspent_outputs = Vec::new();
-        for output in list_outputs.iter() {
-            let tx = conn.get_transaction(&output.transaction_id).await?;
-            unspent_outputs.push(UnspentOutput {
-                tx,
-                output_index: output.output_index,
-            })
+        let total_amount: i32 = unspent_outputs.iter().map(|output| output.amount).sum();
+        let transfer_amount: i32 = recipients.iter().map(|(_, amount)| amount).sum();
+        if transfer_amount > total_amount {
+            anyhow::bail!(RepoError::InsufficientFunds(format!(
+                "insufficient amount of token {token}"
+            )));
         }
-        let unspent_output = unspent_outputs.iter().find(|e| match &e.tx.operation {
-            Some(Operation::CREATE) => {
-                if let Some(id) = &e.tx.id {
-                    return id == token;
-                }
-                false
+
+        // create transaction output
+        let outputs = std::iter::once((sender.public_key.as_str(), total_amount - transfer_amount))
+            .chain(recipients.iter().copied())
+            .filter(|(_, amount)| *amount > 0)
+            .map(|(public_key, amount)| (public_key.to_string(), amount))
+            .collect::<Vec<_>>();
+        let inputs = unspent_outputs
+            .into_iter()
+            .map(|unspent_output| unspent_output.output)
+            .collect::<Vec<_>>();
+
+        // commit transaction to the ledger, signed with sender's private_key
+        self.ledger
+            .transfer(&sender.keypair(), token, &inputs, &outputs, metadata)
+            .await
+    }
+
+    /// Unspent outputs owned by `public_key`, each with the token it holds.
+    pub(super) async fn unspent_outputs(
+        &self,
+        public_key: &str,
+    ) -> anyhow::Result<Vec<UnspentOutput>> {
+        let list_outputs = self.ledger.list_outputs(public_key, Some(false)).await?;
+
+        let mut unspent_outputs = Vec::new();
+        for output in list_outputs {
+            let tx = self.ledger.get_transaction(&output.transaction_id).await?;
+            let amount = match tx.outputs.get(output.output_index) {
+                Some(tx_output) => tx_output.amount.parse()?,
+                None => continue,
+            };
+            if let Some(token) = tx.token() {
+                unspent_outputs.push(UnspentOutput {
+                    token: token.to_string(),
+                    amount,
+                    output,
+                });
             }
-            Some(Operation::TRANSFER) => {
-                if let Some(asset) = &e.tx.asset {
-                    if let Some(id) = asset.get_link_id() {
-                        return id == token;
+        }
+
+        Ok(unspent_outputs)
+    }
+
+    pub async fn get_edge_wallet(&self, edge_id: i32) -> anyhow::Result<EdgeWallet> {
+        self.get_edge_wallets(&[edge_id])
+            .await?
+            .remove(&edge_id)
+            .ok_or_else(|| anyhow::anyhow!(RepoError::NotFound("edge_id not found".to_string())))
+    }
+
+    /// Resolve many edges in a single query, keyed by edge id. Unknown edge
+    /// ids are left out of the result.
+    pub async fn get_edge_wallets(
+        &self,
+        edge_ids: &[i32],
+    ) -> anyhow::Result<HashMap<i32, EdgeWallet>> {
+        if edge_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut edge_wallets = HashMap::with_capacity(edge_ids.len());
+        let mut missing = Vec::new();
+        match &self.cache {
+            Some(cache) => {
+                for &edge_id in edge_ids {
+                    match cache.get(self.tenant_id, edge_id).await {
+                        Some(edge_wallet) => {
+                            edge_wallets.insert(edge_id, edge_wallet);
+                        }
+                        None => missing.push(edge_id),
                     }
                 }
-                false
+                if missing.is_empty() {
+                    return Ok(edge_wallets);
+                }
             }
-            None => false,
-        });
-        let unspent_output = unspent_output.unwrap();
-
-        let total_amount = unspent_output.tx.outputs[unspent_output.output_index]
-            .amount
-            .parse::<i32>()?;
+            None => missing.extend_from_slice(edge_ids),
+        }
 
-        // create transaction output
-        let mut outputs = Vec::new();
-        for (amount, pubkey) in [
-            (total_amount - transfer_amount, &sender.public_key),
-            (transfer_amount, &receiver.public_key),
-        ] {
-            if amount > 0 {
-                outputs.push(Transaction::make_output(
-                    Transaction::make_ed25519_condition(pubkey, true).unwrap(),
-                    amount.to_string(),
-                ));
+        let backend = self.db.get_database_backend();
+        let rows = EdgeWalletRow::find_by_statement(
+            backend.build(&edge_wallets_query(self.tenant_id, &missing)),
+        )
+        .all(&self.db)
+        .await?;
+
+        // rows come one per edge, wallet and token, ordered by edge_id
+        let mut edges: HashMap<i32, EdgeParts> = HashMap::new();
+        for row in rows {
+            let edge = edges.entry(row.edge_id).or_insert_with(|| EdgeParts {
+                closed_at: row.closed_at,
+                src_wallet_id: row.src_wallet_id,
+                dst_wallet_id: row.dst_wallet_id,
+                nft_wallet_id: row.nft_wallet_id,
+                wallets: HashMap::new(),
+                creators: HashMap::new(),
+            });
+            let wallet = edge.wallets.entry(row.wallet_id).or_insert_with(|| Wallet {
+                wallet_id: row.wallet_id,
+                public_key: row.public_key.clone(),
+                private_key: row.private_key.clone(),
+                balances: Vec::new(),
+                closed_at: row.wallet_closed_at,
+            });
+            if let (Some(token_id), Some(token), Some(volume)) =
+                (row.token_id, row.token, row.volume)
+            {
+                if let Some(creator_wallet_id) = row.creator_wallet_id {
+                    edge.creators.insert(token_id, creator_wallet_id);
+                }
+                wallet.balances.push(Balance {
+                    token_id,
+                    token,
+                    volume,
+                });
             }
         }
 
-        // make transfer transaction
-        let transfer_tx = Transaction::make_transfer_transaction(
-            vec![unspent_output.clone()],
-            outputs,
-            Some(serde_json::json!({
-                "transfer_to": &receiver.public_key,
-                "transfer_amount": transfer_amount,
-            })),
-        );
-
-        // signed tranasction with sender's private_key
-        let signed_tx = Transaction::sign_transaction(&transfer_tx, vec![&sender.private_key]);
-
-        // commit tranasction to BigchainDB
-        let _tx = conn.post_transaction_commit(signed_tx).await?;
+        for (edge_id, edge) in edges {
+            let edge_wallet = edge.into_edge_wallet(edge_id)?;
+            if let Some(cache) = &self.cache {
+                cache.put(self.tenant_id, edge_wallet.clone()).await;
+            }
+            edge_wallets.insert(edge_id, edge_wallet);
+        }
 
-        Ok(())
+        Ok(edge_wallets)
     }
 
-    async fn get_wallets_to_tokens(&self, wallet_id: i32) -> anyhow::Result<Wallet> {
-        let wallet = WalletsToTokens::find()
-            .column_as(wallets::Column::Id, "wallet_id")
-            .column_as(tokens::Column::Token, "token")
-            .column_as(wallets::Column::PublicKey, "public_key")
-            .column_as(wallets::Column::PrivateKey, "private_key")
-            .filter(wallets_to_tokens::Column::WalletId.eq(wallet_id))
-            .join(
-                JoinType::InnerJoin,
-                wallets_to_tokens::Relation::Tokens.def(),
-            )
-            .join(
-                JoinType::InnerJoin,
-                wallets_to_tokens::Relation::Wallets.def(),
-            )
-            .into_model::<Wallet>()
-            .one(&self.db)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("dst_wallet_id not found"))?;
-        Ok(wallet)
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_deref().map(EdgeCache::stats)
     }
 
-    pub async fn get_edge_wallet(&self, edge_id: i32) -> anyhow::Result<EdgeWallet> {
-        let edge_to_wallet = self.get_edges_to_wallets(edge_id).await?;
-
-        let src_wallet = self
-            .get_wallets_to_tokens(edge_to_wallet.src_wallet_id)
-            .await?;
-        let dst_wallet = self
-            .get_wallets_to_tokens(edge_to_wallet.dst_wallet_id)
-            .await?;
-        let nft_wallet = self
-            .get_wallets_to_tokens(edge_to_wallet.nft_wallet_id)
-            .await?;
-
-        let token = src_wallet.token.clone();
-        let nft = nft_wallet.token.clone();
-        let resp = EdgeWallet {
-            edge_id,
-            src_wallet,
-            dst_wallet,
-            token,
-            nft,
-        };
+    pub fn node_stats(&self) -> Vec<NodeStats> {
+        self.ledger.stats()
+    }
 
-        Ok(resp)
+    async fn invalidate_edge(&self, edge_id: i32) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate(self.tenant_id, edge_id).await;
+        }
     }
 
-    async fn get_edges_to_wallets(&self, edge_id: i32) -> anyhow::Result<edges_to_wallets::Model> {
-        let record = EdgesToWallets::find()
-            .filter(edges_to_wallets::Column::EdgeId.eq(edge_id))
-            .one(&self.db)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("edge_id not found"))?;
-        Ok(record)
+    /// Drop every cached edge, for mutations that may touch wallets of any edge.
+    async fn invalidate_edges(&self) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate_all().await;
+        }
     }
 
     // async fn get_wallet_by_id(&self, wallet_id: i32) -> anyhow::Result<wallets::Model> {
@@ -329,11 +553,12 @@ impl Repo {
     // }
 
     async fn create_wallet(&self, tx: &DatabaseTransaction) -> Result<wallets::Model, DbErr> {
-        let keypair = ed25519_keypair();
+        let keypair = self.ledger.generate_keypair();
 
         let wallet = wallets::ActiveModel {
-            public_key: Set(keypair.pk),
-            private_key: Set(keypair.sk),
+            public_key: Set(keypair.public_key),
+            private_key: Set(keypair.private_key),
+            tenant_id: Set(self.tenant_id),
             ..Default::default()
         }
         .save(tx)
@@ -347,28 +572,33 @@ impl Repo {
         &self,
         signer: &wallets::Model,
         init_amount: i32,
+        kind: TokenKind,
         asset: Option<serde_json::Value>,
         metadata: Option<serde_json::Value>,
         db_tx: &DatabaseTransaction,
     ) -> Result<tokens::Model, DbErr> {
-        let condition = Transaction::make_ed25519_condition(&signer.public_key, true).unwrap();
-        let output = Transaction::make_output(condition, init_amount.to_string());
-        let tx = Transaction::make_create_transaction(
-            asset,
-            metadata,
-            vec![output],
-            vec![signer.private_key.to_string()],
-        );
-        let signed_tx = Transaction::sign_transaction(&tx, vec![&signer.private_key]);
-
-        let mut conn = Connection::new(vec![&self.bigchain_url]);
-        let bigchain_tx = conn
-            .post_transaction_commit(signed_tx)
+        let transaction_id = self
+            .ledger
+            .create(
+                &KeyPair {
+                    public_key: signer.public_key.clone(),
+                    private_key: signer.private_key.clone(),
+                },
+                init_amount,
+                asset.clone(),
+                metadata.clone(),
+            )
             .await
             .map_err(|_| DbErr::Custom("BigchainDB post transaction error".to_string()))?;
 
         let token = tokens::ActiveModel {
-            token: Set(bigchain_tx.id.clone().unwrap()),
+            token: Set(transaction_id),
+            asset: Set(asset),
+            metadata: Set(metadata),
+            supply: Set(Some(init_amount)),
+            kind: Set(Some(kind)),
+            creator_wallet_id: Set(Some(signer.id)),
+            tenant_id: Set(self.tenant_id),
             ..Default::default()
         }
         .save(db_tx)
@@ -389,6 +619,8 @@ impl Repo {
             token_id: Set(token_id),
             wallet_id: Set(wallet_id),
             volume: Set(amount),
+            tenant_id: Set(self.tenant_id),
+            ..Default::default()
         };
         let record = model.insert(tx).await?;
         Ok(record)
@@ -407,6 +639,7 @@ impl Repo {
             src_wallet_id: Set(src_wallet_id),
             dst_wallet_id: Set(dst_wallet_id),
             nft_wallet_id: Set(nft_wallet_id),
+            tenant_id: Set(self.tenant_id),
             ..Default::default()
         }
         .save(tx)
@@ -415,3 +648,145 @@ impl Repo {
         Ok(model)
     }
 }
+
+#[derive(FromQueryResult)]
+struct EdgeWalletRow {
+    edge_id: i32,
+    closed_at: Option<DateTimeWithTimeZone>,
+    src_wallet_id: i32,
+    dst_wallet_id: i32,
+    nft_wallet_id: i32,
+
+    wallet_id: i32,
+    public_key: String,
+    private_key: String,
+    wallet_closed_at: Option<DateTimeWithTimeZone>,
+
+    token_id: Option<i32>,
+    token: Option<String>,
+    volume: Option<i32>,
+    creator_wallet_id: Option<i32>,
+}
+
+struct EdgeParts {
+    closed_at: Option<DateTimeWithTimeZone>,
+    src_wallet_id: i32,
+    dst_wallet_id: i32,
+    nft_wallet_id: i32,
+    wallets: HashMap<i32, Wallet>,
+    /// Minting wallet of every token held by the edge's wallets.
+    creators: HashMap<i32, i32>,
+}
+
+impl EdgeParts {
+    /// The token a wallet minted, falling back to the first one it holds for
+    /// tokens whose creator is unknown.
+    fn minted_by(&self, wallet: &Wallet) -> Option<Balance> {
+        wallet
+            .balances
+            .iter()
+            .find(|balance| self.creators.get(&balance.token_id) == Some(&wallet.wallet_id))
+            .or_else(|| wallet.balances.first())
+            .cloned()
+    }
+
+    fn into_edge_wallet(mut self, edge_id: i32) -> anyhow::Result<EdgeWallet> {
+        let mut take = |wallet_id: i32| {
+            self.wallets.remove(&wallet_id).ok_or_else(|| {
+                anyhow::anyhow!("wallet_id {wallet_id} of edge_id {edge_id} not found")
+            })
+        };
+        let src_wallet = take(self.src_wallet_id)?;
+        let dst_wallet = take(self.dst_wallet_id)?;
+        let nft_wallet = take(self.nft_wallet_id)?;
+
+        let token = self
+            .minted_by(&src_wallet)
+            .ok_or_else(|| anyhow::anyhow!("token of edge_id {edge_id} not found"))?;
+        let nft = self
+            .minted_by(&nft_wallet)
+            .ok_or_else(|| anyhow::anyhow!("nft of edge_id {edge_id} not found"))?;
+
+        Ok(EdgeWallet {
+            edge_id,
+            src_wallet,
+            dst_wallet,
+            token: token.token,
+            token_id: token.token_id,
+            nft: nft.token,
+            closed_at: self.closed_at,
+        })
+    }
+}
+
+/// Join every edge of `tenant_id` to its three wallets and each of their
+/// balances, one row per edge, wallet and token held.
+fn edge_wallets_query(tenant_id: i32, edge_ids: &[i32]) -> SelectStatement {
+    let edge = Alias::new("e");
+    let wallet = Alias::new("w");
+    let balance = Alias::new("wt");
+    let token = Alias::new("t");
+
+    Query::select()
+        .column((edge.clone(), edges_to_wallets::Column::EdgeId))
+        .column((edge.clone(), edges_to_wallets::Column::ClosedAt))
+        .column((edge.clone(), edges_to_wallets::Column::SrcWalletId))
+        .column((edge.clone(), edges_to_wallets::Column::DstWalletId))
+        .column((edge.clone(), edges_to_wallets::Column::NftWalletId))
+        .expr_as(
+            Expr::col((wallet.clone(), wallets::Column::Id)),
+            Alias::new("wallet_id"),
+        )
+        .column((wallet.clone(), wallets::Column::PublicKey))
+        .column((wallet.clone(), wallets::Column::PrivateKey))
+        .expr_as(
+            Expr::col((wallet.clone(), wallets::Column::ClosedAt)),
+            Alias::new("wallet_closed_at"),
+        )
+        .column((balance.clone(), wallets_to_tokens::Column::TokenId))
+        .column((balance.clone(), wallets_to_tokens::Column::Volume))
+        .column((token.clone(), tokens::Column::Token))
+        .column((token.clone(), tokens::Column::CreatorWalletId))
+        .from_as(EdgesToWallets, edge.clone())
+        .join_as(
+            JoinType::InnerJoin,
+            Wallets,
+            wallet.clone(),
+            Condition::any()
+                .add(
+                    Expr::col((wallet.clone(), wallets::Column::Id))
+                        .equals((edge.clone(), edges_to_wallets::Column::SrcWalletId)),
+                )
+                .add(
+                    Expr::col((wallet.clone(), wallets::Column::Id))
+                        .equals((edge.clone(), edges_to_wallets::Column::DstWalletId)),
+                )
+                .add(
+                    Expr::col((wallet.clone(), wallets::Column::Id))
+                        .equals((edge.clone(), edges_to_wallets::Column::NftWalletId)),
+                ),
+        )
+        .join_as(
+            JoinType::LeftJoin,
+            WalletsToTokens,
+            balance.clone(),
+            Expr::col((balance.clone(), wallets_to_tokens::Column::WalletId))
+                .equals((wallet.clone(), wallets::Column::Id)),
+        )
+        .join_as(
+            JoinType::LeftJoin,
+            Tokens,
+            token.clone(),
+            Expr::col((token, tokens::Column::Id))
+                .equals((balance.clone(), wallets_to_tokens::Column::TokenId)),
+        )
+        .and_where(Expr::col((edge.clone(), edges_to_wallets::Column::TenantId)).eq(tenant_id))
+        .and_where(
+            Expr::col((edge.clone(), edges_to_wallets::Column::EdgeId))
+                .is_in(edge_ids.iter().copied()),
+        )
+        .order_by((edge, edges_to_wallets::Column::EdgeId), Order::Asc)
+        .order_by((wallet, wallets::Column::Id), Order::Asc)
+        .order_by((balance, wallets_to_tokens::Column::TokenId), Order::Asc)
+        .to_owned()
+}
@@ -0,0 +1,62 @@
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, ColumnTrait, IntoActiveModel, QueryFilter};
+
+use super::Repo;
+use crate::entity::{prelude::*, sea_orm_active_enums::TokenKind, *};
+
+impl Repo {
+    /// Fill in asset, metadata, supply, kind and creator of tokens minted
+    /// before those columns existed, reading them back from each token's
+    /// CREATE transaction. Returns the number of tokens updated.
+    pub async fn backfill_tokens(&self) -> anyhow::Result<usize> {
+        let records = self
+            .find::<Tokens>()
+            .filter(tokens::Column::Kind.is_null())
+            .all(&self.db)
+            .await?;
+
+        let mut updated = 0;
+        for record in records {
+            let create_tx = self.ledger.get_transaction(&record.token).await?;
+
+            let mut supply = 0;
+            for output in create_tx.outputs.iter() {
+                supply += output.amount.parse::<i32>()?;
+            }
+            let kind = match supply {
+                1 => TokenKind::NonFungible,
+                _ => TokenKind::Fungible,
+            };
+
+            let creator_wallet_id = match create_tx
+                .outputs
+                .first()
+                .and_then(|output| output.public_keys.first())
+            {
+                Some(public_key) => self
+                    .find::<Wallets>()
+                    .filter(wallets::Column::PublicKey.eq(public_key))
+                    .one(&self.db)
+                    .await?
+                    .map(|wallet| wallet.id),
+                None => None,
+            };
+
+            let asset = Some(create_tx.asset["data"].clone()).filter(|v| !v.is_null());
+            let metadata = Some(create_tx.metadata).filter(|v| !v.is_null());
+
+            let mut record = record.into_active_model();
+            record.asset = Set(asset);
+            record.metadata = Set(metadata);
+            record.supply = Set(Some(supply));
+            record.kind = Set(Some(kind));
+            record.creator_wallet_id = Set(creator_wallet_id);
+            let _ = record.update(&self.db).await?;
+            updated += 1;
+        }
+        if updated > 0 {
+            self.invalidate_edges().await;
+        }
+
+        Ok(updated)
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use sea_orm::TransactionTrait;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::{transaction_error, Repo, RepoError, TxError, Wallet};
+use crate::{entity::prelude::*, events::Event};
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct Payout {
+    pub to_wallet_id: i32,
+    pub amount: i32,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct BatchTransfer {
+    pub from_wallet_id: i32,
+    pub token: String,
+    pub payouts: Vec<Payout>,
+}
+
+impl Repo {
+    /// Pay out tokens from one wallet to many wallets with a single
+    /// multi-output TRANSFER, returning the sender followed by every recipient.
+    pub async fn batch_transfer(
+        self: Arc<Self>,
+        data: BatchTransfer,
+    ) -> anyhow::Result<Vec<Wallet>> {
+        if data.payouts.is_empty() {
+            anyhow::bail!(RepoError::Invalid(
+                "batch transfer has no payouts".to_string()
+            ));
+        }
+
+        let sender = self.get_wallet(data.from_wallet_id).await?;
+        if sender.closed_at.is_some() {
+            anyhow::bail!(RepoError::Conflict("from_wallet_id is closed".to_string()));
+        }
+        let token_id = sender
+            .balances
+            .iter()
+            .find(|balance| balance.token == data.token)
+            .map(|balance| balance.token_id)
+            .ok_or_else(|| {
+                anyhow::anyhow!(RepoError::InsufficientFunds(
+                    "from_wallet_id does not hold token".to_string()
+                ))
+            })?;
+
+        let mut total_amount: i32 = 0;
+        let mut receivers = Vec::with_capacity(data.payouts.len());
+        for payout in data.payouts.iter() {
+            if payout.amount <= 0 {
+                anyhow::bail!(RepoError::Invalid(
+                    "payout amount must be positive".to_string()
+                ));
+            }
+            if payout.to_wallet_id == sender.wallet_id {
+                anyhow::bail!(RepoError::Invalid(
+                    "payout to the sending wallet".to_string()
+                ));
+            }
+            total_amount = total_amount.checked_add(payout.amount).ok_or_else(|| {
+                anyhow::anyhow!(RepoError::Invalid("payout amount overflow".to_string()))
+            })?;
+
+            let receiver = self
+                .find_by_id::<Wallets, _>(payout.to_wallet_id)
+                .one(&self.db)
+                .await?
+                .ok_or_else(|| {
+                    anyhow::anyhow!(RepoError::NotFound("to_wallet_id not found".to_string()))
+                })?;
+            if receiver.closed_at.is_some() {
+                anyhow::bail!(RepoError::Conflict("to_wallet_id is closed".to_string()));
+            }
+            receivers.push(receiver);
+        }
+        if sender.volume(&data.token) < total_amount {
+            anyhow::bail!(RepoError::InsufficientFunds(
+                "insufficient src_wallet volume".to_string()
+            ));
+        }
+
+        let recipients = receivers
+            .iter()
+            .zip(data.payouts.iter())
+            .map(|(receiver, payout)| (receiver.public_key.as_str(), payout.amount))
+            .collect::<Vec<_>>();
+        let metadata = serde_json::json!({
+            "transfers": recipients
+                .iter()
+                .map(|(public_key, amount)| serde_json::json!({
+                    "transfer_to": public_key,
+                    "transfer_amount": amount,
+                }))
+                .collect::<Vec<_>>(),
+        });
+        let transaction_id = self
+            .bigchain_transfer(&sender, &data.token, &recipients, metadata)
+            .await?;
+
+        let events = receivers
+            .iter()
+            .zip(data.payouts.iter())
+            .map(|(receiver, payout)| Event::TokensTransferred {
+                edge_id: None,
+                token: data.token.clone(),
+                from_public_key: sender.public_key.clone(),
+                to_public_key: receiver.public_key.clone(),
+                amount: payout.amount,
+            })
+            .collect::<Vec<_>>();
+
+        let _self = self.clone();
+        let sender_id = sender.wallet_id;
+        _self
+            .db
+            .transaction::<_, (), TxError>(|tx| {
+                Box::pin(async move {
+                    // the ledger sync may have applied it already
+                    if let Some(ledger_transaction_id) = self
+                        .claim_transaction(&transaction_id, "TRANSFER", &data.token, None, tx)
+                        .await?
+                    {
+                        for payout in data.payouts.iter() {
+                            self.move_volume(
+                                sender_id,
+                                payout.to_wallet_id,
+                                token_id,
+                                payout.amount,
+                                ledger_transaction_id,
+                                tx,
+                            )
+                            .await?;
+                        }
+                    }
+                    for event in events {
+                        self.record_event(event, tx).await?;
+                    }
+
+                    Ok(())
+                })
+            })
+            .await
+            .map_err(transaction_error)?;
+        _self.invalidate_edges().await;
+
+        let wallet_ids = std::iter::once(sender_id)
+            .chain(receivers.iter().map(|receiver| receiver.id))
+            .collect::<Vec<_>>();
+        _self.get_wallets(&wallet_ids).await
+    }
+}
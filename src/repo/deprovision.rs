@@ -0,0 +1,186 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue::Set, ColumnTrait, IntoActiveModel,
+    PaginatorTrait, QueryFilter, QuerySelect, TransactionTrait,
+};
+
+use super::{transaction_error, EdgeWallet, Repo, RepoError, TxError, Wallet};
+use crate::{
+    entity::{
+        prelude::*,
+        sea_orm_active_enums::{EscrowState, TokenKind},
+        *,
+    },
+    events::Event,
+};
+
+impl Repo {
+    /// Close an edge and its wallets, sweeping every remaining FT and NFT
+    /// balance to the treasury. Rows are kept for audit.
+    ///
+    /// The edge is closed before anything is swept, so calling this again on
+    /// an already closed edge resumes a sweep that failed half way.
+    pub async fn deprovision_edge(self: Arc<Self>, edge_id: i32) -> anyhow::Result<EdgeWallet> {
+        let Some(treasury) = self.treasury_public_key.clone() else {
+            anyhow::bail!("treasury.public_key is required to deprovision edges");
+        };
+        let open_escrows = self
+            .find::<Escrows>()
+            .filter(escrows::Column::EdgeId.eq(edge_id))
+            .filter(escrows::Column::State.is_in([
+                EscrowState::Open,
+                EscrowState::Releasing,
+                EscrowState::Refunding,
+            ]))
+            .count(&self.db)
+            .await?;
+        if open_escrows > 0 {
+            anyhow::bail!(RepoError::Conflict("edge_id has open escrows".to_string()));
+        }
+
+        let _self = self.clone();
+        let edge_to_wallet = _self
+            .db
+            .transaction::<_, edges_to_wallets::Model, TxError>(|tx| {
+                Box::pin(async move {
+                    let edge_to_wallet = self
+                        .find::<EdgesToWallets>()
+                        .filter(edges_to_wallets::Column::EdgeId.eq(edge_id))
+                        .lock_exclusive()
+                        .one(tx)
+                        .await?
+                        .ok_or_else(|| RepoError::NotFound("edge_id not found".to_string()))?;
+                    if edge_to_wallet.closed_at.is_some() {
+                        return Ok(edge_to_wallet);
+                    }
+
+                    let closed_at = chrono::Utc::now().fixed_offset();
+                    let _ = self
+                        .update_many::<Wallets>()
+                        .col_expr(wallets::Column::ClosedAt, Expr::value(closed_at))
+                        .col_expr(wallets::Column::UpdatedAt, Expr::value(closed_at))
+                        .filter(wallets::Column::Id.is_in([
+                            edge_to_wallet.src_wallet_id,
+                            edge_to_wallet.dst_wallet_id,
+                            edge_to_wallet.nft_wallet_id,
+                        ]))
+                        .exec(tx)
+                        .await?;
+
+                    let mut edge_to_wallet = edge_to_wallet.into_active_model();
+                    edge_to_wallet.closed_at = Set(Some(closed_at));
+                    let edge_to_wallet = edge_to_wallet.update(tx).await?;
+
+                    self.record_event(Event::EdgeDeprovisioned { edge_id }, tx)
+                        .await?;
+
+                    Ok(edge_to_wallet)
+                })
+            })
+            .await
+            .map_err(transaction_error)?;
+        _self.invalidate_edge(edge_id).await;
+
+        for wallet_id in [
+            edge_to_wallet.src_wallet_id,
+            edge_to_wallet.dst_wallet_id,
+            edge_to_wallet.nft_wallet_id,
+        ] {
+            let wallet = _self.get_wallet(wallet_id).await?;
+            // the ledger holds what is left to sweep, whatever the balances say
+            let mut amounts = BTreeMap::<String, i32>::new();
+            for unspent_output in _self.unspent_outputs(&wallet.public_key).await? {
+                *amounts.entry(unspent_output.token).or_default() += unspent_output.amount;
+            }
+            for (token, amount) in amounts {
+                let Some(token) = _self
+                    .find::<Tokens>()
+                    .filter(tokens::Column::Token.eq(token))
+                    .one(&_self.db)
+                    .await?
+                else {
+                    continue;
+                };
+                _self
+                    .sweep_to_treasury(edge_id, &wallet, &token, amount, &treasury)
+                    .await?;
+            }
+        }
+        _self.invalidate_edge(edge_id).await;
+
+        _self.get_edge_wallet(edge_id).await
+    }
+
+    async fn sweep_to_treasury(
+        &self,
+        edge_id: i32,
+        wallet: &Wallet,
+        token: &tokens::Model,
+        amount: i32,
+        treasury: &str,
+    ) -> anyhow::Result<()> {
+        if amount <= 0 {
+            return Ok(());
+        }
+
+        let transaction_id = self
+            .bigchain_transfer(
+                wallet,
+                &token.token,
+                &[(treasury, amount)],
+                serde_json::json!({
+                    "deprovision_edge": edge_id,
+                    "transfer_to": treasury,
+                    "transfer_amount": amount,
+                }),
+            )
+            .await?;
+
+        let tx = self.db.begin().await?;
+        // the ledger sync may have applied it already
+        if let Some(ledger_transaction_id) = self
+            .claim_transaction(&transaction_id, "TRANSFER", &token.token, None, &tx)
+            .await?
+        {
+            let wallet_to_token = self
+                .find_by_id::<WalletsToTokens, _>((wallet.wallet_id, token.id))
+                .one(&tx)
+                .await?;
+            if let Some(wallet_to_token) = wallet_to_token.filter(|w| w.volume != 0) {
+                let volume = wallet_to_token.volume;
+                let mut wallet_to_token = wallet_to_token.into_active_model();
+                wallet_to_token.volume = Set(0);
+                let _ = wallet_to_token.update(&tx).await?;
+                self.record_balance_change(
+                    ledger_transaction_id,
+                    wallet.wallet_id,
+                    token.id,
+                    -volume,
+                    &tx,
+                )
+                .await?;
+            }
+        }
+
+        let event = match token.kind {
+            Some(TokenKind::NonFungible) => Event::NftTransferred {
+                edge_id,
+                nft: token.token.clone(),
+                from_public_key: wallet.public_key.clone(),
+                to_public_key: treasury.to_string(),
+            },
+            _ => Event::TokensTransferred {
+                edge_id: Some(edge_id),
+                token: token.token.clone(),
+                from_public_key: wallet.public_key.clone(),
+                to_public_key: treasury.to_string(),
+                amount,
+            },
+        };
+        self.record_event(event, &tx).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::fmt;
+
+use sea_orm::{DbErr, TransactionError};
+
+/// Why a `Repo` operation refused a request. Returned inside
+/// `anyhow::Error`, recover it with `downcast_ref`. Failures of the database
+/// or the ledger are passed through as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoError {
+    NotFound(String),
+    /// The request itself is malformed.
+    Invalid(String),
+    /// The request conflicts with the current state, e.g. a closed edge.
+    Conflict(String),
+    InsufficientFunds(String),
+    /// No valid API key was presented.
+    Unauthenticated(String),
+    /// The API client lacks the scope or the edge the request needs.
+    Forbidden(String),
+}
+
+impl fmt::Display for RepoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepoError::NotFound(msg)
+            | RepoError::Invalid(msg)
+            | RepoError::Conflict(msg)
+            | RepoError::InsufficientFunds(msg)
+            | RepoError::Unauthenticated(msg)
+            | RepoError::Forbidden(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for RepoError {}
+
+/// Error rolling back a `Repo` database transaction. Unlike
+/// `DbErr::Custom`, it carries a [`RepoError`] or a ledger failure raised
+/// inside the transaction to the caller intact, see [`transaction_error`].
+#[derive(Debug)]
+pub(crate) enum TxError {
+    Db(DbErr),
+    /// A refusal, e.g. a [`RepoError`], or a ledger failure.
+    Other(anyhow::Error),
+}
+
+impl From<DbErr> for TxError {
+    fn from(e: DbErr) -> Self {
+        TxError::Db(e)
+    }
+}
+
+impl From<RepoError> for TxError {
+    fn from(e: RepoError) -> Self {
+        TxError::Other(e.into())
+    }
+}
+
+impl From<anyhow::Error> for TxError {
+    fn from(e: anyhow::Error) -> Self {
+        TxError::Other(e)
+    }
+}
+
+impl fmt::Display for TxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxError::Db(e) => e.fmt(f),
+            TxError::Other(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for TxError {}
+
+/// The error of a transaction run with [`TxError`], unwrapped so that
+/// callers can still `downcast_ref` a [`RepoError`] out of it.
+pub(crate) fn transaction_error(e: TransactionError<TxError>) -> anyhow::Error {
+    match e {
+        TransactionError::Connection(e) | TransactionError::Transaction(TxError::Db(e)) => e.into(),
+        TransactionError::Transaction(TxError::Other(e)) => e,
+    }
+}
@@ -0,0 +1,370 @@
+use std::{sync::Arc, time::Duration};
+
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, ActiveModelTrait, ActiveValue::Set, ColumnTrait, Condition,
+    EntityTrait, IntoActiveModel, QueryFilter, QuerySelect, TransactionTrait,
+};
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+
+use super::{transaction_error, Repo, RepoError, TxError, Wallet};
+use crate::{
+    entity::{prelude::*, sea_orm_active_enums::EscrowState, *},
+    events::Event,
+    ledger::LedgerError,
+};
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct OpenEscrow {
+    pub edge_id: i32,
+    pub amount: i32,
+    #[schema(value_type = String, format = DateTime)]
+    pub deadline: DateTimeWithTimeZone,
+}
+
+impl Repo {
+    /// Lock `amount` FT units of the edge's `src_wallet` in a freshly created
+    /// escrow wallet until they are released or refunded.
+    ///
+    /// The escrow wallet is stored before the units are paid to it: should
+    /// recording the escrow fail afterwards, the wallet and its key are kept
+    /// and `reconcile` reports the units missing from `src_wallet`.
+    pub async fn open_escrow(self: Arc<Self>, data: OpenEscrow) -> anyhow::Result<escrows::Model> {
+        if data.amount <= 0 {
+            anyhow::bail!(RepoError::Invalid(
+                "escrow amount must be positive".to_string()
+            ));
+        }
+        if data.deadline <= chrono::Utc::now() {
+            anyhow::bail!(RepoError::Invalid(
+                "escrow deadline must be in the future".to_string()
+            ));
+        }
+
+        let edge_wallet = self.get_edge_wallet(data.edge_id).await?;
+        if edge_wallet.closed_at.is_some() {
+            anyhow::bail!(RepoError::Conflict("edge_id is closed".to_string()));
+        }
+        if edge_wallet.src_wallet.volume(&edge_wallet.token) < data.amount {
+            anyhow::bail!(RepoError::InsufficientFunds(
+                "insufficient src_wallet volume".to_string()
+            ));
+        }
+
+        // commit the escrow wallet's key before anything is paid to it, so
+        // that the units are never stranded on a key that was rolled back
+        let tx = self.db.begin().await?;
+        let escrow_wallet = self.create_wallet(&tx).await?;
+        tx.commit().await?;
+
+        let src_wallet = edge_wallet.src_wallet;
+        let receiver = Wallet {
+            wallet_id: escrow_wallet.id,
+            public_key: escrow_wallet.public_key,
+            private_key: escrow_wallet.private_key,
+            balances: Vec::new(),
+            closed_at: None,
+        };
+        let transaction_id = self
+            .bigchain_transfer_token(&src_wallet, &receiver, &edge_wallet.token, data.amount)
+            .await?;
+
+        let _self = self.clone();
+        let escrow = _self
+            .db
+            .transaction::<_, escrows::Model, TxError>(|tx| {
+                Box::pin(async move {
+                    // the ledger sync may have applied it already
+                    if let Some(ledger_transaction_id) = self
+                        .claim_transaction(
+                            &transaction_id,
+                            "TRANSFER",
+                            &edge_wallet.token,
+                            None,
+                            tx,
+                        )
+                        .await?
+                    {
+                        self.move_volume(
+                            src_wallet.wallet_id,
+                            receiver.wallet_id,
+                            edge_wallet.token_id,
+                            data.amount,
+                            ledger_transaction_id,
+                            tx,
+                        )
+                        .await?;
+                    }
+
+                    let escrow = escrows::ActiveModel {
+                        edge_id: Set(data.edge_id),
+                        escrow_wallet_id: Set(receiver.wallet_id),
+                        token_id: Set(edge_wallet.token_id),
+                        amount: Set(data.amount),
+                        state: Set(EscrowState::Open),
+                        deadline: Set(data.deadline),
+                        tenant_id: Set(self.tenant_id),
+                        ..Default::default()
+                    }
+                    .insert(tx)
+                    .await?;
+
+                    self.record_event(
+                        Event::TokensTransferred {
+                            edge_id: Some(escrow.edge_id),
+                            token: edge_wallet.token,
+                            from_public_key: src_wallet.public_key,
+                            to_public_key: receiver.public_key,
+                            amount: escrow.amount,
+                        },
+                        tx,
+                    )
+                    .await?;
+                    self.record_event(
+                        Event::EscrowOpened {
+                            escrow_id: escrow.id,
+                            edge_id: escrow.edge_id,
+                            amount: escrow.amount,
+                            deadline: escrow.deadline,
+                        },
+                        tx,
+                    )
+                    .await?;
+
+                    Ok(escrow)
+                })
+            })
+            .await
+            .map_err(transaction_error)?;
+        _self.invalidate_edge(escrow.edge_id).await;
+
+        Ok(escrow)
+    }
+
+    /// Pay the escrowed units out to the edge's `dst_wallet`.
+    pub async fn release_escrow(self: Arc<Self>, escrow_id: i32) -> anyhow::Result<escrows::Model> {
+        self.settle_escrow(escrow_id, EscrowState::Released).await
+    }
+
+    /// Return the escrowed units to the edge's `src_wallet`.
+    pub async fn refund_escrow(self: Arc<Self>, escrow_id: i32) -> anyhow::Result<escrows::Model> {
+        self.settle_escrow(escrow_id, EscrowState::Refunded).await
+    }
+
+    /// Refund every open escrow whose deadline has passed, of every tenant,
+    /// and resume the refunds left half way, returning the escrows that were
+    /// refunded.
+    pub async fn refund_expired_escrows(self: Arc<Self>) -> anyhow::Result<Vec<escrows::Model>> {
+        let expired = Escrows::find()
+            .filter(
+                Condition::any()
+                    .add(
+                        escrows::Column::State
+                            .eq(EscrowState::Open)
+                            .and(escrows::Column::Deadline.lte(chrono::Utc::now())),
+                    )
+                    .add(escrows::Column::State.eq(EscrowState::Refunding)),
+            )
+            .all(&self.db)
+            .await?;
+
+        let mut refunded = Vec::with_capacity(expired.len());
+        for escrow in expired {
+            match self
+                .for_tenant(escrow.tenant_id)
+                .refund_escrow(escrow.id)
+                .await
+            {
+                Ok(escrow) => refunded.push(escrow),
+                Err(e) => log::warn!("refund escrow {} error: {e:?}", escrow.id),
+            }
+        }
+
+        Ok(refunded)
+    }
+
+    /// Spawn a background task calling [`Repo::refund_expired_escrows`] every `period`.
+    pub fn spawn_escrow_sweeper(self: Arc<Self>, period: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.clone().refund_expired_escrows().await {
+                    log::error!("escrow sweeper error: {e:?}");
+                }
+            }
+        })
+    }
+
+    /// Pay an escrow out in the three steps `open_escrow` takes: mark it
+    /// releasing or refunding, post the transfer, then apply it to the
+    /// balances. Calling this again on an escrow left half way resumes it;
+    /// when its wallet holds nothing on the ledger anymore the transfer
+    /// already went through, and its balances are left to the ledger sync.
+    async fn settle_escrow(
+        self: Arc<Self>,
+        escrow_id: i32,
+        state: EscrowState,
+    ) -> anyhow::Result<escrows::Model> {
+        let settling = match state {
+            EscrowState::Released => EscrowState::Releasing,
+            _ => EscrowState::Refunding,
+        };
+
+        let tx = self.db.begin().await?;
+        // lock the escrow row so a release and a refund cannot both settle it
+        let escrow = self
+            .find_by_id::<Escrows, _>(escrow_id)
+            .lock_exclusive()
+            .one(&tx)
+            .await?
+            .ok_or_else(|| RepoError::NotFound("escrow_id not found".to_string()))?;
+        let resumed = escrow.state == settling;
+        if !resumed {
+            if escrow.state != EscrowState::Open {
+                anyhow::bail!(RepoError::Conflict("escrow already settled".to_string()));
+            }
+            if state == EscrowState::Released && escrow.deadline <= chrono::Utc::now() {
+                anyhow::bail!(RepoError::Conflict("escrow deadline passed".to_string()));
+            }
+            let mut escrow = escrow.clone().into_active_model();
+            escrow.state = Set(settling.clone());
+            escrow.update(&tx).await?;
+        }
+        tx.commit().await?;
+
+        let edge_wallet = self.get_edge_wallet(escrow.edge_id).await?;
+        let escrow_wallet = self.get_wallet(escrow.escrow_wallet_id).await?;
+        let token = escrow_wallet
+            .balances
+            .iter()
+            .find(|balance| balance.token_id == escrow.token_id)
+            .map(|balance| balance.token.clone())
+            .ok_or_else(|| anyhow::anyhow!("escrow wallet holds no escrowed token"))?;
+        let receiver = match state {
+            EscrowState::Released => edge_wallet.dst_wallet,
+            _ => edge_wallet.src_wallet,
+        };
+
+        let posted = resumed
+            && !self
+                .unspent_outputs(&escrow_wallet.public_key)
+                .await?
+                .iter()
+                .any(|unspent_output| unspent_output.token == token);
+        let transaction_id = match posted {
+            true => None,
+            false => match self
+                .bigchain_transfer_token(&escrow_wallet, &receiver, &token, escrow.amount)
+                .await
+            {
+                Ok(transaction_id) => Some(transaction_id),
+                Err(e) => {
+                    // refused before anything was posted: the escrow is open again
+                    if !resumed && refused(&e) {
+                        Escrows::update_many()
+                            .set(escrows::ActiveModel {
+                                state: Set(EscrowState::Open),
+                                updated_at: Set(chrono::Utc::now().fixed_offset()),
+                                ..Default::default()
+                            })
+                            .filter(escrows::Column::Id.eq(escrow_id))
+                            .filter(escrows::Column::State.eq(settling))
+                            .exec(&self.db)
+                            .await?;
+                    }
+                    return Err(e);
+                }
+            },
+        };
+
+        let _self = self.clone();
+        let escrow = _self
+            .db
+            .transaction::<_, escrows::Model, TxError>(|tx| {
+                Box::pin(async move {
+                    let escrow = self
+                        .find_by_id::<Escrows, _>(escrow_id)
+                        .lock_exclusive()
+                        .one(tx)
+                        .await?
+                        .ok_or_else(|| RepoError::NotFound("escrow_id not found".to_string()))?;
+                    if escrow.state != settling {
+                        return Err(
+                            RepoError::Conflict("escrow already settled".to_string()).into()
+                        );
+                    }
+
+                    // the ledger sync may have applied it already
+                    if let Some(transaction_id) = transaction_id {
+                        if let Some(ledger_transaction_id) = self
+                            .claim_transaction(&transaction_id, "TRANSFER", &token, None, tx)
+                            .await?
+                        {
+                            self.move_volume(
+                                escrow_wallet.wallet_id,
+                                receiver.wallet_id,
+                                escrow.token_id,
+                                escrow.amount,
+                                ledger_transaction_id,
+                                tx,
+                            )
+                            .await?;
+                        }
+                    }
+
+                    let mut escrow = escrow.into_active_model();
+                    escrow.state = Set(state.clone());
+                    let escrow = escrow.update(tx).await?;
+
+                    self.record_event(
+                        Event::TokensTransferred {
+                            edge_id: Some(escrow.edge_id),
+                            token,
+                            from_public_key: escrow_wallet.public_key,
+                            to_public_key: receiver.public_key,
+                            amount: escrow.amount,
+                        },
+                        tx,
+                    )
+                    .await?;
+                    let event = match state {
+                        EscrowState::Released => Event::EscrowReleased {
+                            escrow_id: escrow.id,
+                            edge_id: escrow.edge_id,
+                            amount: escrow.amount,
+                        },
+                        _ => Event::EscrowRefunded {
+                            escrow_id: escrow.id,
+                            edge_id: escrow.edge_id,
+                            amount: escrow.amount,
+                        },
+                    };
+                    self.record_event(event, tx).await?;
+
+                    Ok(escrow)
+                })
+            })
+            .await
+            .map_err(transaction_error)?;
+        _self.invalidate_edge(escrow.edge_id).await;
+
+        Ok(escrow)
+    }
+}
+
+/// Whether a failed transfer certainly never reached the ledger.
+fn refused(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| {
+        cause.is::<RepoError>()
+            || matches!(
+                cause.downcast_ref(),
+                Some(
+                    LedgerError::NotFound(_)
+                        | LedgerError::Rejected(_)
+                        | LedgerError::Unavailable(_)
+                )
+            )
+    })
+}
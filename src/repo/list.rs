@@ -0,0 +1,448 @@
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, sea_query::Expr, ColumnTrait, Condition, IntoSimpleExpr,
+    JoinType, Order, QueryFilter, QueryOrder, QuerySelect, QueryTrait, RelationTrait, Value,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::{EdgeWallet, Repo, RepoError, Wallet};
+use crate::entity::{prelude::*, tokens::Model as Token, *};
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 500;
+
+#[derive(Deserialize, ToSchema, clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl From<SortOrder> for Order {
+    fn from(order: SortOrder) -> Self {
+        match order {
+            SortOrder::Asc => Order::Asc,
+            SortOrder::Desc => Order::Desc,
+        }
+    }
+}
+
+/// What a listing is sorted by, ties broken by id.
+#[derive(Deserialize, ToSchema, clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    #[default]
+    Id,
+    CreatedAt,
+    /// The volume of `token`, wallets only.
+    Volume,
+}
+
+/// One page of a listing. Pass `next_cursor` back as `after` to fetch the
+/// following page; it is `None` on the last page.
+#[derive(Serialize, ToSchema, Debug)]
+#[aliases(
+    EdgeWalletPage = Page<EdgeWallet>,
+    WalletPage = Page<Wallet>,
+    TokenPage = Page<Token>
+)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Default)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+pub struct ListEdges {
+    /// Only edges whose FT or NFT is this token.
+    pub token: Option<String>,
+    /// Only edges owning a wallet with this public key.
+    pub public_key: Option<String>,
+    pub closed: Option<bool>,
+    pub sort: SortBy,
+    pub order: SortOrder,
+    pub after: Option<String>,
+    /// Page size, 50 when unset and at most 500.
+    pub limit: Option<u64>,
+    /// Only these edges, for API clients restricted to some edges.
+    #[serde(skip)]
+    pub edge_ids: Option<Vec<i32>>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Default)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+pub struct ListWallets {
+    pub token: Option<String>,
+    pub public_key: Option<String>,
+    pub min_volume: Option<i32>,
+    pub max_volume: Option<i32>,
+    pub sort: SortBy,
+    pub order: SortOrder,
+    pub after: Option<String>,
+    /// Page size, 50 when unset and at most 500.
+    pub limit: Option<u64>,
+}
+
+#[derive(Deserialize, IntoParams, Debug, Default)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+pub struct ListTokens {
+    pub token: Option<String>,
+    /// Only tokens held by a wallet with this public key.
+    pub public_key: Option<String>,
+    pub sort: SortBy,
+    pub order: SortOrder,
+    pub after: Option<String>,
+    /// Page size, 50 when unset and at most 500.
+    pub limit: Option<u64>,
+}
+
+impl Repo {
+    /// List edges ordered by provisioning order.
+    pub async fn list_edges(&self, query: ListEdges) -> anyhow::Result<Page<EdgeWallet>> {
+        let records = self.list_edge_records(query).await?;
+
+        let edge_ids = records
+            .items
+            .iter()
+            .map(|record| record.edge_id)
+            .collect::<Vec<_>>();
+        let mut edge_wallets = self.get_edge_wallets(&edge_ids).await?;
+        let items = edge_ids
+            .iter()
+            .filter_map(|edge_id| edge_wallets.remove(edge_id))
+            .collect();
+
+        Ok(Page {
+            items,
+            next_cursor: records.next_cursor,
+        })
+    }
+
+    /// Like [`Repo::list_edges`], returning the `edges_to_wallets` rows only.
+    pub async fn list_edge_records(
+        &self,
+        query: ListEdges,
+    ) -> anyhow::Result<Page<edges_to_wallets::Model>> {
+        let limit = page_size(query.limit)?;
+        if query.sort == SortBy::Volume {
+            anyhow::bail!(RepoError::Invalid(
+                "only wallets can be sorted by volume".to_string()
+            ));
+        }
+        let mut select = self.find::<EdgesToWallets>();
+
+        if let Some(token) = query.token {
+            let wallet_ids = self
+                .find::<WalletsToTokens>()
+                .select_only()
+                .column(wallets_to_tokens::Column::WalletId)
+                .join(
+                    JoinType::InnerJoin,
+                    wallets_to_tokens::Relation::Tokens.def(),
+                )
+                .filter(tokens::Column::Token.eq(token))
+                .into_query();
+            select = select.filter(
+                Condition::any()
+                    .add(edges_to_wallets::Column::SrcWalletId.in_subquery(wallet_ids.clone()))
+                    .add(edges_to_wallets::Column::NftWalletId.in_subquery(wallet_ids)),
+            );
+        }
+        if let Some(public_key) = query.public_key {
+            let wallet_ids = self
+                .find::<Wallets>()
+                .select_only()
+                .column(wallets::Column::Id)
+                .filter(wallets::Column::PublicKey.eq(public_key))
+                .into_query();
+            select = select.filter(
+                Condition::any()
+                    .add(edges_to_wallets::Column::SrcWalletId.in_subquery(wallet_ids.clone()))
+                    .add(edges_to_wallets::Column::DstWalletId.in_subquery(wallet_ids.clone()))
+                    .add(edges_to_wallets::Column::NftWalletId.in_subquery(wallet_ids)),
+            );
+        }
+        if let Some(edge_ids) = query.edge_ids {
+            select = select.filter(edges_to_wallets::Column::EdgeId.is_in(edge_ids));
+        }
+        if let Some(closed) = query.closed {
+            select = select.filter(match closed {
+                true => edges_to_wallets::Column::ClosedAt.is_not_null(),
+                false => edges_to_wallets::Column::ClosedAt.is_null(),
+            });
+        }
+        let key = match query.sort {
+            SortBy::CreatedAt => edges_to_wallets::Column::CreatedAt,
+            _ => edges_to_wallets::Column::Id,
+        };
+        if let Some(after) = query.after {
+            let after = parse_cursor(&after, query.sort)?;
+            select = select.filter(after.seek(key, edges_to_wallets::Column::Id, query.order));
+        }
+
+        let mut records = select
+            .order_by(key, query.order.into())
+            .order_by(edges_to_wallets::Column::Id, query.order.into())
+            .limit(limit + 1)
+            .all(&self.db)
+            .await?;
+        let next_cursor = next_page(&mut records, limit)
+            .map(|record| cursor(query.sort, &record.created_at, None, record.id));
+
+        Ok(Page {
+            items: records,
+            next_cursor,
+        })
+    }
+
+    /// List wallets with all of their balances. The token and volume
+    /// filters keep wallets holding at least one matching balance.
+    pub async fn list_wallets(&self, query: ListWallets) -> anyhow::Result<Page<Wallet>> {
+        let records = self.list_wallet_records(query).await?;
+
+        let wallet_ids = records
+            .items
+            .iter()
+            .map(|record| record.id)
+            .collect::<Vec<_>>();
+        let items = self.get_wallets(&wallet_ids).await?;
+
+        Ok(Page {
+            items,
+            next_cursor: records.next_cursor,
+        })
+    }
+
+    /// Like [`Repo::list_wallets`], returning the `wallets` rows only.
+    pub async fn list_wallet_records(
+        &self,
+        query: ListWallets,
+    ) -> anyhow::Result<Page<wallets::Model>> {
+        let limit = page_size(query.limit)?;
+        let mut select = self.find::<Wallets>();
+
+        if query.sort == SortBy::Volume {
+            let token = query.token.clone().ok_or_else(|| {
+                anyhow::anyhow!(RepoError::Invalid(
+                    "sorting by volume needs a token".to_string()
+                ))
+            })?;
+            select = select
+                .join(
+                    JoinType::InnerJoin,
+                    wallets::Relation::WalletsToTokens.def(),
+                )
+                .join(
+                    JoinType::InnerJoin,
+                    wallets_to_tokens::Relation::Tokens.def(),
+                )
+                .filter(tokens::Column::Token.eq(token));
+        }
+        let token = query.token.clone();
+        if query.token.is_some() || query.min_volume.is_some() || query.max_volume.is_some() {
+            let mut balances = self
+                .find::<WalletsToTokens>()
+                .select_only()
+                .column(wallets_to_tokens::Column::WalletId)
+                .join(
+                    JoinType::InnerJoin,
+                    wallets_to_tokens::Relation::Tokens.def(),
+                );
+            if let Some(token) = query.token {
+                balances = balances.filter(tokens::Column::Token.eq(token));
+            }
+            if let Some(min_volume) = query.min_volume {
+                balances = balances.filter(wallets_to_tokens::Column::Volume.gte(min_volume));
+            }
+            if let Some(max_volume) = query.max_volume {
+                balances = balances.filter(wallets_to_tokens::Column::Volume.lte(max_volume));
+            }
+            select = select.filter(wallets::Column::Id.in_subquery(balances.into_query()));
+        }
+        if let Some(public_key) = query.public_key {
+            select = select.filter(wallets::Column::PublicKey.eq(public_key));
+        }
+        let key = match query.sort {
+            SortBy::Id => wallets::Column::Id.into_simple_expr(),
+            SortBy::CreatedAt => wallets::Column::CreatedAt.into_simple_expr(),
+            SortBy::Volume => wallets_to_tokens::Column::Volume.into_simple_expr(),
+        };
+        if let Some(after) = query.after {
+            let after = parse_cursor(&after, query.sort)?;
+            select = select.filter(after.seek(key.clone(), wallets::Column::Id, query.order));
+        }
+
+        let mut records = select
+            .order_by(key, query.order.into())
+            .order_by(wallets::Column::Id, query.order.into())
+            .limit(limit + 1)
+            .all(&self.db)
+            .await?;
+        let next_cursor = match next_page(&mut records, limit) {
+            Some(record) => {
+                let volume = match (query.sort, token) {
+                    (SortBy::Volume, Some(token)) => {
+                        self.find::<WalletsToTokens>()
+                            .select_only()
+                            .column(wallets_to_tokens::Column::Volume)
+                            .join(
+                                JoinType::InnerJoin,
+                                wallets_to_tokens::Relation::Tokens.def(),
+                            )
+                            .filter(wallets_to_tokens::Column::WalletId.eq(record.id))
+                            .filter(tokens::Column::Token.eq(token))
+                            .into_tuple::<i32>()
+                            .one(&self.db)
+                            .await?
+                    }
+                    _ => None,
+                };
+                Some(cursor(query.sort, &record.created_at, volume, record.id))
+            }
+            None => None,
+        };
+
+        Ok(Page {
+            items: records,
+            next_cursor,
+        })
+    }
+
+    /// List tokens ordered by mint order.
+    pub async fn list_tokens(&self, query: ListTokens) -> anyhow::Result<Page<tokens::Model>> {
+        let limit = page_size(query.limit)?;
+        if query.sort == SortBy::Volume {
+            anyhow::bail!(RepoError::Invalid(
+                "only wallets can be sorted by volume".to_string()
+            ));
+        }
+        let mut select = self.find::<Tokens>();
+
+        if let Some(token) = query.token {
+            select = select.filter(tokens::Column::Token.eq(token));
+        }
+        if let Some(public_key) = query.public_key {
+            let token_ids = self
+                .find::<WalletsToTokens>()
+                .select_only()
+                .column(wallets_to_tokens::Column::TokenId)
+                .join(
+                    JoinType::InnerJoin,
+                    wallets_to_tokens::Relation::Wallets.def(),
+                )
+                .filter(wallets::Column::PublicKey.eq(public_key))
+                .into_query();
+            select = select.filter(tokens::Column::Id.in_subquery(token_ids));
+        }
+        let key = match query.sort {
+            SortBy::CreatedAt => tokens::Column::CreatedAt,
+            _ => tokens::Column::Id,
+        };
+        if let Some(after) = query.after {
+            let after = parse_cursor(&after, query.sort)?;
+            select = select.filter(after.seek(key, tokens::Column::Id, query.order));
+        }
+
+        let mut items = select
+            .order_by(key, query.order.into())
+            .order_by(tokens::Column::Id, query.order.into())
+            .limit(limit + 1)
+            .all(&self.db)
+            .await?;
+        let next_cursor = next_page(&mut items, limit)
+            .map(|token| cursor(query.sort, &token.created_at, None, token.id));
+
+        Ok(Page { items, next_cursor })
+    }
+}
+
+fn page_size(limit: Option<u64>) -> anyhow::Result<u64> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        anyhow::bail!(RepoError::Invalid(format!(
+            "limit must be between 1 and {MAX_PAGE_SIZE}"
+        )));
+    }
+    Ok(limit)
+}
+
+/// Where a page ended: the sort key of its last row, unless sorted by id,
+/// and the row's id. Written as `id` or `key,id`, the key of `created_at`
+/// in nanoseconds.
+struct Cursor {
+    key: Option<Value>,
+    id: i32,
+}
+
+impl Cursor {
+    /// Rows past the cursor in `order`: past its key, or at its key and past
+    /// its id.
+    fn seek(&self, key: impl IntoSimpleExpr, id: impl ColumnTrait, order: SortOrder) -> Condition {
+        let past_id = match order {
+            SortOrder::Asc => id.gt(self.id),
+            SortOrder::Desc => id.lt(self.id),
+        };
+        let Some(value) = self.key.clone() else {
+            return Condition::all().add(past_id);
+        };
+        let key = Expr::expr(key.into_simple_expr());
+        let past_key = match order {
+            SortOrder::Asc => key.clone().gt(value.clone()),
+            SortOrder::Desc => key.clone().lt(value.clone()),
+        };
+        Condition::any()
+            .add(past_key)
+            .add(Condition::all().add(key.eq(value)).add(past_id))
+    }
+}
+
+fn cursor(sort: SortBy, created_at: &DateTimeWithTimeZone, volume: Option<i32>, id: i32) -> String {
+    match sort {
+        SortBy::Id => id.to_string(),
+        SortBy::CreatedAt => format!(
+            "{},{id}",
+            created_at.timestamp_nanos_opt().unwrap_or_default()
+        ),
+        SortBy::Volume => format!("{},{id}", volume.unwrap_or_default()),
+    }
+}
+
+fn parse_cursor(cursor: &str, sort: SortBy) -> anyhow::Result<Cursor> {
+    let invalid = || anyhow::anyhow!(RepoError::Invalid("invalid cursor".to_string()));
+    let (key, id) = match sort {
+        SortBy::Id => (None, cursor),
+        _ => {
+            let (key, id) = cursor.split_once(',').ok_or_else(invalid)?;
+            (Some(key.parse::<i64>().map_err(|_| invalid())?), id)
+        }
+    };
+    let key = match (sort, key) {
+        (SortBy::CreatedAt, Some(nanos)) => Some(
+            chrono::DateTime::from_timestamp_nanos(nanos)
+                .fixed_offset()
+                .into(),
+        ),
+        (SortBy::Volume, Some(volume)) => {
+            Some(i32::try_from(volume).map_err(|_| invalid())?.into())
+        }
+        _ => None,
+    };
+    Ok(Cursor {
+        key,
+        id: id.parse().map_err(|_| invalid())?,
+    })
+}
+
+/// Trim the extra row fetched past `limit`, returning the last row kept when
+/// a next page follows.
+fn next_page<T>(items: &mut Vec<T>, limit: u64) -> Option<&T> {
+    if items.len() as u64 <= limit {
+        return None;
+    }
+    items.truncate(limit as usize);
+    items.last()
+}
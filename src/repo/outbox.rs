@@ -0,0 +1,108 @@
+use std::{sync::Arc, time::Duration};
+
+use sea_orm::{
+    sea_query::{Expr, LockBehavior, LockType},
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    TransactionTrait,
+};
+use tokio::task::JoinHandle;
+
+use super::Repo;
+use crate::{
+    entity::{prelude::*, *},
+    events::{Event, EventRecord, EventSink},
+};
+
+/// Events handed to a sink per outbox transaction.
+const RELAY_BATCH: u64 = 100;
+
+impl Repo {
+    /// Record `event` in the outbox on `db`, which must be the transaction
+    /// doing the work the event reports so that both commit or neither does.
+    pub(super) async fn record_event<C: ConnectionTrait>(
+        &self,
+        event: Event,
+        db: &C,
+    ) -> Result<(), DbErr> {
+        let event = serde_json::to_value(event).map_err(|e| DbErr::Json(e.to_string()))?;
+        outbox_events::ActiveModel {
+            tenant_id: Set(self.tenant_id),
+            event: Set(event),
+            created_at: Set(chrono::Utc::now().fixed_offset()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Hand up to `limit` unpublished events of every tenant to `sink`, oldest
+    /// first, and mark the ones it accepted as published. Stops at the first
+    /// event the sink fails on, which is retried on the next call. Returns
+    /// how many events were published.
+    pub async fn publish_events(&self, sink: &dyn EventSink, limit: u64) -> anyhow::Result<usize> {
+        let tx = self.db.begin().await?;
+        // skip events another relay is publishing
+        let pending = OutboxEvents::find()
+            .filter(outbox_events::Column::PublishedAt.is_null())
+            .order_by_asc(outbox_events::Column::Id)
+            .limit(limit)
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+            .all(&tx)
+            .await?;
+
+        let mut published = Vec::with_capacity(pending.len());
+        let mut result = Ok(());
+        for model in pending {
+            let id = model.id;
+            result = match EventRecord::try_from(model) {
+                Ok(event) => sink.publish(&event).await,
+                Err(e) => Err(e),
+            };
+            if result.is_err() {
+                break;
+            }
+            published.push(id);
+        }
+
+        if !published.is_empty() {
+            OutboxEvents::update_many()
+                .col_expr(
+                    outbox_events::Column::PublishedAt,
+                    Expr::value(chrono::Utc::now().fixed_offset()),
+                )
+                .filter(outbox_events::Column::Id.is_in(published.iter().copied()))
+                .exec(&tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        result.map(|()| published.len())
+    }
+
+    /// Spawn a background task draining the outbox into `sink` every `period`.
+    pub fn spawn_event_relay(
+        self: Arc<Self>,
+        sink: Arc<dyn EventSink>,
+        period: Duration,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                loop {
+                    match self.publish_events(sink.as_ref(), RELAY_BATCH).await {
+                        Ok(published) if published as u64 == RELAY_BATCH => continue,
+                        Ok(_) => break,
+                        Err(e) => {
+                            log::error!("event relay error: {e:?}");
+                            break;
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use super::{Repo, Wallet};
+
+/// A token whose volume recorded for a wallet differs from the amount its
+/// unspent outputs hold on the ledger.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Discrepancy {
+    pub edge_id: i32,
+    pub public_key: String,
+    pub token: String,
+    pub recorded: i32,
+    pub ledger: i32,
+}
+
+impl Repo {
+    /// Compare the balances recorded for the `src_wallet` and `dst_wallet`
+    /// of each edge with the ledger. Nothing is corrected, unknown edges are
+    /// left out.
+    pub async fn reconcile_edges(&self, edge_ids: &[i32]) -> anyhow::Result<Vec<Discrepancy>> {
+        let edge_wallets = self.get_edge_wallets(edge_ids).await?;
+
+        let mut discrepancies = Vec::new();
+        for edge_id in edge_ids {
+            let Some(edge_wallet) = edge_wallets.get(edge_id) else {
+                continue;
+            };
+            for wallet in [&edge_wallet.src_wallet, &edge_wallet.dst_wallet] {
+                discrepancies.extend(self.reconcile_wallet(*edge_id, wallet).await?);
+            }
+        }
+
+        Ok(discrepancies)
+    }
+
+    async fn reconcile_wallet(
+        &self,
+        edge_id: i32,
+        wallet: &Wallet,
+    ) -> anyhow::Result<Vec<Discrepancy>> {
+        let mut ledger: HashMap<String, i32> = HashMap::new();
+        for unspent_output in self.unspent_outputs(&wallet.public_key).await? {
+            *ledger.entry(unspent_output.token).or_default() += unspent_output.amount;
+        }
+
+        // (recorded, ledger) per token, ordered for a stable report
+        let mut volumes: BTreeMap<String, (i32, i32)> = BTreeMap::new();
+        for balance in wallet.balances.iter() {
+            volumes.entry(balance.token.clone()).or_default().0 = balance.volume;
+        }
+        for (token, amount) in ledger {
+            volumes.entry(token).or_default().1 = amount;
+        }
+
+        Ok(volumes
+            .into_iter()
+            .filter(|(_, (recorded, ledger))| recorded != ledger)
+            .map(|(token, (recorded, ledger))| Discrepancy {
+                edge_id,
+                public_key: wallet.public_key.clone(),
+                token,
+                recorded,
+                ledger,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,452 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::StreamExt;
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, sea_query::OnConflict, ActiveModelTrait, ActiveValue::Set,
+    ColumnTrait, ConnectionTrait, DatabaseTransaction, DbErr, EntityTrait, FromQueryResult,
+    IntoActiveModel, JoinType, QueryFilter, QueryOrder, QuerySelect, RelationTrait,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+
+use super::Repo;
+use crate::{
+    entity::{prelude::*, *},
+    ledger::{Block, Transaction},
+};
+
+/// Cursor of the valid transactions stream in `ledger_cursors`.
+const STREAM_CURSOR: &str = "valid_transactions";
+
+/// A change of a wallet's balance, and the ledger transaction causing it.
+#[derive(Serialize, FromQueryResult, Debug, Clone, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub transaction_id: String,
+    pub operation: String,
+    pub token: String,
+    /// Units received, negative for units spent.
+    pub amount: i32,
+    /// Block the transaction was seen in on the stream, `None` for the
+    /// transactions this service posted itself.
+    pub height: Option<i64>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+/// A message of the valid transactions stream.
+#[derive(Deserialize)]
+struct StreamMessage {
+    height: i64,
+}
+
+/// A transaction of a block that moves tokens of known wallets.
+struct Touching {
+    transaction_id: String,
+    operation: String,
+    token: String,
+    /// Net amount received per wallet.
+    amounts: BTreeMap<i32, i32>,
+}
+
+impl Repo {
+    /// Record that the ledger transaction `transaction_id` is reflected in
+    /// the balances, on `db`, which must be the transaction updating them.
+    /// Returns `None` when it already is, in which case the balances must be
+    /// left alone: whoever claimed it first applied it.
+    pub(super) async fn claim_transaction<C: ConnectionTrait>(
+        &self,
+        transaction_id: &str,
+        operation: &str,
+        token: &str,
+        height: Option<i64>,
+        db: &C,
+    ) -> Result<Option<i32>, DbErr> {
+        let claimed = LedgerTransactions::insert(ledger_transactions::ActiveModel {
+            transaction_id: Set(transaction_id.to_string()),
+            operation: Set(operation.to_string()),
+            token: Set(token.to_string()),
+            height: Set(height),
+            created_at: Set(chrono::Utc::now().fixed_offset()),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::column(ledger_transactions::Column::TransactionId)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec(db)
+        .await;
+        match claimed {
+            Ok(claimed) => Ok(Some(claimed.last_insert_id)),
+            Err(DbErr::RecordNotInserted) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Add `amount` units of a token to the history of a wallet.
+    pub(super) async fn record_balance_change<C: ConnectionTrait>(
+        &self,
+        ledger_transaction_id: i32,
+        wallet_id: i32,
+        token_id: i32,
+        amount: i32,
+        db: &C,
+    ) -> Result<(), DbErr> {
+        if amount == 0 {
+            return Ok(());
+        }
+        balance_changes::ActiveModel {
+            tenant_id: Set(self.tenant_id),
+            ledger_transaction_id: Set(ledger_transaction_id),
+            wallet_id: Set(wallet_id),
+            token_id: Set(token_id),
+            amount: Set(amount),
+            created_at: Set(chrono::Utc::now().fixed_offset()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Every balance change of a wallet, oldest first.
+    pub async fn wallet_history(&self, wallet_id: i32) -> anyhow::Result<Vec<BalanceChange>> {
+        // not found rather than an empty history for another tenant's wallet
+        self.get_wallet(wallet_id).await?;
+
+        Ok(self
+            .find::<BalanceChanges>()
+            .select_only()
+            .column_as(ledger_transactions::Column::TransactionId, "transaction_id")
+            .column_as(ledger_transactions::Column::Operation, "operation")
+            .column_as(tokens::Column::Token, "token")
+            .column_as(balance_changes::Column::Amount, "amount")
+            .column_as(ledger_transactions::Column::Height, "height")
+            .column_as(balance_changes::Column::CreatedAt, "created_at")
+            .join(
+                JoinType::InnerJoin,
+                balance_changes::Relation::LedgerTransactions.def(),
+            )
+            .join(JoinType::InnerJoin, balance_changes::Relation::Tokens.def())
+            .filter(balance_changes::Column::WalletId.eq(wallet_id))
+            .order_by_asc(balance_changes::Column::Id)
+            .into_model::<BalanceChange>()
+            .all(&self.db)
+            .await?)
+    }
+
+    /// Height of the last block applied to the balances, `None` before the
+    /// first sync.
+    pub async fn ledger_cursor(&self) -> anyhow::Result<Option<i64>> {
+        Ok(LedgerCursors::find_by_id(STREAM_CURSOR)
+            .one(&self.db)
+            .await?
+            .map(|cursor| cursor.height))
+    }
+
+    /// Start syncing at block `height` unless a sync already started.
+    /// Earlier blocks are never applied, so that transactions this service
+    /// posted before it recorded them are not counted twice.
+    pub async fn start_ledger_sync(&self, height: i64) -> anyhow::Result<()> {
+        let cursor = ledger_cursors::ActiveModel {
+            name: Set(STREAM_CURSOR.to_string()),
+            height: Set(height - 1),
+            updated_at: Set(chrono::Utc::now().fixed_offset()),
+        };
+        match LedgerCursors::insert(cursor)
+            .on_conflict(
+                OnConflict::column(ledger_cursors::Column::Name)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await
+        {
+            Ok(_) | Err(DbErr::RecordNotInserted) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Apply the block after the cursor to the balances of every tenant's
+    /// wallets and advance the cursor. Returns the height applied, `None`
+    /// before the first sync or when the ledger has no further block yet.
+    pub async fn sync_next_block(&self) -> anyhow::Result<Option<i64>> {
+        let Some(cursor) = self.ledger_cursor().await? else {
+            return Ok(None);
+        };
+        let height = cursor + 1;
+        let Some(block) = self.ledger.get_block(height).await? else {
+            return Ok(None);
+        };
+
+        // read everything from the ledger before holding any lock
+        let (wallets, touching) = self.touching_wallets(&block).await?;
+
+        let tx = self.db.begin().await?;
+        let cursor = LedgerCursors::find_by_id(STREAM_CURSOR)
+            .lock_exclusive()
+            .one(&tx)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("ledger cursor vanished"))?;
+        if cursor.height != height - 1 {
+            // another listener applied the block meanwhile
+            return Ok(Some(cursor.height));
+        }
+
+        let mut applied = false;
+        for transaction in touching {
+            let Some(ledger_transaction_id) = self
+                .claim_transaction(
+                    &transaction.transaction_id,
+                    &transaction.operation,
+                    &transaction.token,
+                    Some(height),
+                    &tx,
+                )
+                .await?
+            else {
+                continue;
+            };
+            for (wallet_id, amount) in transaction.amounts {
+                let tenant = self.for_tenant(wallets[&wallet_id].tenant_id);
+                tenant
+                    .apply_balance_change(
+                        ledger_transaction_id,
+                        wallet_id,
+                        &transaction.token,
+                        amount,
+                        &tx,
+                    )
+                    .await?;
+            }
+            applied = true;
+        }
+
+        let mut cursor = cursor.into_active_model();
+        cursor.height = Set(height);
+        cursor.updated_at = Set(chrono::Utc::now().fixed_offset());
+        cursor.update(&tx).await?;
+        tx.commit().await?;
+
+        if applied {
+            self.invalidate_edges().await;
+        }
+        Ok(Some(height))
+    }
+
+    /// Apply every block the ledger has after the cursor. Returns how many
+    /// were applied.
+    pub async fn catch_up_ledger(&self) -> anyhow::Result<usize> {
+        let mut applied = 0;
+        while self.sync_next_block().await?.is_some() {
+            applied += 1;
+        }
+        Ok(applied)
+    }
+
+    /// Spawn a background task following the valid transactions stream at
+    /// `stream_url`, or the one the nodes advertise, and applying each block
+    /// to the balances. Blocks missed while disconnected or stopped are
+    /// applied on reconnection, which is attempted every `reconnect`.
+    pub fn spawn_ledger_sync(
+        self: Arc<Self>,
+        stream_url: Option<String>,
+        reconnect: Duration,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.follow_stream(stream_url.as_deref()).await {
+                    log::error!("ledger sync error: {e:?}");
+                }
+                tokio::time::sleep(reconnect).await;
+            }
+        })
+    }
+
+    async fn follow_stream(&self, stream_url: Option<&str>) -> anyhow::Result<()> {
+        let stream_url = match stream_url {
+            Some(stream_url) => stream_url.to_string(),
+            None => self.ledger.stream_url().await?,
+        };
+        let (mut stream, _) = tokio_tungstenite::connect_async(&stream_url).await?;
+        // blocks committed while we were away
+        self.catch_up_ledger().await?;
+
+        while let Some(message) = stream.next().await {
+            let Message::Text(message) = message? else {
+                continue;
+            };
+            let message: StreamMessage = serde_json::from_str(&message)?;
+            self.start_ledger_sync(message.height).await?;
+            while self
+                .sync_next_block()
+                .await?
+                .is_some_and(|height| height < message.height)
+            {}
+        }
+
+        anyhow::bail!("stream {stream_url} closed")
+    }
+
+    /// The wallets of every tenant the transactions of `block` pay or spend
+    /// from, and those transactions with the net amount each wallet
+    /// received. Outputs and inputs with several owners are left out.
+    async fn touching_wallets(
+        &self,
+        block: &Block,
+    ) -> anyhow::Result<(HashMap<i32, wallets::Model>, Vec<Touching>)> {
+        let public_keys = block
+            .transactions
+            .iter()
+            .flat_map(|transaction| {
+                let paid = transaction.outputs.iter().map(|output| &output.public_keys);
+                let spent = transaction.inputs.iter().map(|input| &input.owners_before);
+                paid.chain(spent).flatten().cloned()
+            })
+            .collect::<Vec<_>>();
+        let wallets = Wallets::find()
+            .filter(wallets::Column::PublicKey.is_in(public_keys))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|wallet| (wallet.public_key.clone(), wallet))
+            .collect::<HashMap<_, _>>();
+        if wallets.is_empty() {
+            return Ok((HashMap::new(), Vec::new()));
+        }
+
+        let mut touching = Vec::new();
+        for transaction in block.transactions.iter() {
+            let Some(token) = transaction.token() else {
+                continue;
+            };
+            let mut amounts: BTreeMap<i32, i32> = BTreeMap::new();
+            for output in transaction.outputs.iter() {
+                if let [public_key] = output.public_keys.as_slice() {
+                    if let Some(wallet) = wallets.get(public_key) {
+                        *amounts.entry(wallet.id).or_default() += output.amount.parse::<i32>()?;
+                    }
+                }
+            }
+            for (public_key, spent) in self.spent_outputs(transaction, &wallets).await? {
+                *amounts.entry(wallets[&public_key].id).or_default() -= spent;
+            }
+            amounts.retain(|_, amount| *amount != 0);
+
+            if !amounts.is_empty() {
+                touching.push(Touching {
+                    transaction_id: transaction.id.clone(),
+                    operation: transaction.operation.clone(),
+                    token: token.to_string(),
+                    amounts,
+                });
+            }
+        }
+
+        let wallets = wallets
+            .into_values()
+            .map(|wallet| (wallet.id, wallet))
+            .collect();
+        Ok((wallets, touching))
+    }
+
+    /// `(public_key, amount)` of every output of a known wallet `transaction`
+    /// spends, read from the transactions that created them.
+    async fn spent_outputs(
+        &self,
+        transaction: &Transaction,
+        wallets: &HashMap<String, wallets::Model>,
+    ) -> anyhow::Result<Vec<(String, i32)>> {
+        let mut spent = Vec::new();
+        for input in transaction.inputs.iter() {
+            let (Some(fulfills), [public_key]) = (&input.fulfills, input.owners_before.as_slice())
+            else {
+                continue;
+            };
+            if !wallets.contains_key(public_key) {
+                continue;
+            }
+            let source = self
+                .ledger
+                .get_transaction(&fulfills.transaction_id)
+                .await?;
+            let output = source.outputs.get(fulfills.output_index).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "output {} of {} not found",
+                    fulfills.output_index,
+                    fulfills.transaction_id
+                )
+            })?;
+            spent.push((public_key.clone(), output.amount.parse()?));
+        }
+        Ok(spent)
+    }
+
+    /// Add `amount` units of `token` to a wallet of this repo's tenant, never
+    /// going below zero, and record the units actually added in its history,
+    /// so the history always sums to the balance. Tokens the tenant has not
+    /// seen yet are recorded, for [`Repo::backfill_tokens`] to fill in.
+    async fn apply_balance_change(
+        &self,
+        ledger_transaction_id: i32,
+        wallet_id: i32,
+        token: &str,
+        amount: i32,
+        tx: &DatabaseTransaction,
+    ) -> Result<(), DbErr> {
+        let token_id = match self
+            .find::<Tokens>()
+            .filter(tokens::Column::Token.eq(token))
+            .one(tx)
+            .await?
+        {
+            Some(token) => token.id,
+            None => {
+                tokens::ActiveModel {
+                    token: Set(token.to_string()),
+                    tenant_id: Set(self.tenant_id),
+                    ..Default::default()
+                }
+                .insert(tx)
+                .await?
+                .id
+            }
+        };
+
+        let applied = match self
+            .find_by_id::<WalletsToTokens, _>((wallet_id, token_id))
+            .one(tx)
+            .await?
+        {
+            Some(balance) => {
+                let before = balance.volume;
+                let volume = (before + amount).max(0);
+                let mut balance = balance.into_active_model();
+                balance.volume = Set(volume);
+                balance.update(tx).await?;
+                volume - before
+            }
+            None => {
+                let volume = amount.max(0);
+                self.create_wallet_to_token(wallet_id, token_id, volume, tx)
+                    .await?;
+                volume
+            }
+        };
+        if applied != amount {
+            log::warn!(
+                "ledger transaction {ledger_transaction_id} spends {} units of {token} \
+                 wallet {wallet_id} does not hold, left to reconcile",
+                applied - amount
+            );
+        }
+
+        self.record_balance_change(ledger_transaction_id, wallet_id, token_id, applied, tx)
+            .await
+    }
+}
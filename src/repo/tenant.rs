@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use sea_orm::{ColumnTrait, EntityTrait, PrimaryKeyTrait, QueryFilter, Select, UpdateMany};
+
+use super::Repo;
+use crate::entity::*;
+
+/// Tenant of rows written before tenants existed, and of a `Repo` built from
+/// configuration.
+pub const DEFAULT_TENANT_ID: i32 = 0;
+
+/// An entity whose rows belong to a tenant.
+pub trait TenantScoped: EntityTrait {
+    fn tenant_column() -> Self::Column;
+}
+
+macro_rules! impl_tenant_scoped {
+    ($($entity:ident),*) => {
+        $(impl TenantScoped for $entity::Entity {
+            fn tenant_column() -> Self::Column {
+                $entity::Column::TenantId
+            }
+        })*
+    };
+}
+
+impl_tenant_scoped!(
+    api_clients,
+    balance_changes,
+    edges_to_wallets,
+    escrows,
+    tokens,
+    wallets,
+    wallets_to_tokens,
+    webhook_deliveries,
+    webhook_subscriptions
+);
+
+impl Repo {
+    /// A handle on the rows of `tenant_id`, sharing this repo's connection
+    /// pool, ledger nodes and cache. Everything it reads is filtered to the
+    /// tenant and everything it writes is stamped with it.
+    pub fn for_tenant(&self, tenant_id: i32) -> Arc<Repo> {
+        Arc::new(Repo {
+            db: self.db.clone(),
+            ledger: self.ledger.clone(),
+            ft_supply: self.ft_supply,
+            mint_metadata: self.mint_metadata.clone(),
+            treasury_public_key: self.treasury_public_key.clone(),
+            cache: self.cache.clone(),
+            tenant_id,
+        })
+    }
+
+    /// `E::find()` limited to this repo's tenant.
+    pub fn find<E: TenantScoped>(&self) -> Select<E> {
+        E::find().filter(E::tenant_column().eq(self.tenant_id))
+    }
+
+    /// `E::find_by_id()` limited to this repo's tenant.
+    pub fn find_by_id<E, T>(&self, id: T) -> Select<E>
+    where
+        E: TenantScoped,
+        T: Into<<E::PrimaryKey as PrimaryKeyTrait>::ValueType>,
+    {
+        E::find_by_id(id).filter(E::tenant_column().eq(self.tenant_id))
+    }
+
+    /// `E::update_many()` limited to this repo's tenant.
+    pub fn update_many<E: TenantScoped>(&self) -> UpdateMany<E> {
+        E::update_many().filter(E::tenant_column().eq(self.tenant_id))
+    }
+}
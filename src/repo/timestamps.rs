@@ -0,0 +1,37 @@
+use sea_orm::{ActiveModelBehavior, ActiveValue::Set, ConnectionTrait, DbErr};
+
+use crate::entity::*;
+
+/// Stamps `created_at` when a row is inserted, unless it is given, and
+/// `updated_at` on every save. The generated entity files of these tables
+/// leave out their `impl ActiveModelBehavior`, drop it again after
+/// regenerating them.
+macro_rules! impl_timestamps {
+    ($($entity:ident),*) => {
+        $(#[async_trait::async_trait]
+        impl ActiveModelBehavior for $entity::ActiveModel {
+            async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+            where
+                C: ConnectionTrait,
+            {
+                let now = chrono::Utc::now().fixed_offset();
+                if insert && self.created_at.is_not_set() {
+                    self.created_at = Set(now);
+                }
+                self.updated_at = Set(now);
+                Ok(self)
+            }
+        })*
+    };
+}
+
+impl_timestamps!(
+    api_clients,
+    edges_to_wallets,
+    escrows,
+    tokens,
+    wallets,
+    wallets_to_tokens,
+    webhook_deliveries,
+    webhook_subscriptions
+);
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseTransaction, FromQueryResult,
+    IntoActiveModel, JoinType, QueryFilter, QueryOrder, QuerySelect, RelationTrait,
+};
+
+use super::{Balance, Repo, RepoError, TxError, Wallet};
+use crate::entity::{prelude::*, *};
+
+#[derive(FromQueryResult)]
+struct BalanceRow {
+    wallet_id: i32,
+    token_id: i32,
+    token: String,
+    volume: i32,
+}
+
+impl Repo {
+    /// Every token held by a wallet together with its volume.
+    pub async fn get_wallet_balances(&self, wallet_id: i32) -> anyhow::Result<Vec<Balance>> {
+        Ok(self.get_wallet(wallet_id).await?.balances)
+    }
+
+    pub(super) async fn get_wallet(&self, wallet_id: i32) -> anyhow::Result<Wallet> {
+        self.get_wallets(&[wallet_id])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!(RepoError::NotFound("wallet_id not found".to_string())))
+    }
+
+    /// Load wallets with their balances in the order of `wallet_ids`, leaving
+    /// out unknown ids.
+    pub(super) async fn get_wallets(&self, wallet_ids: &[i32]) -> anyhow::Result<Vec<Wallet>> {
+        let records = self
+            .find::<Wallets>()
+            .filter(wallets::Column::Id.is_in(wallet_ids.iter().copied()))
+            .all(&self.db)
+            .await?;
+        let mut balances = self.get_balances(wallet_ids).await?;
+
+        let mut wallets = records
+            .into_iter()
+            .map(|record| {
+                (
+                    record.id,
+                    Wallet {
+                        wallet_id: record.id,
+                        balances: balances.remove(&record.id).unwrap_or_default(),
+                        public_key: record.public_key,
+                        private_key: record.private_key,
+                        closed_at: record.closed_at,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        Ok(wallet_ids
+            .iter()
+            .filter_map(|wallet_id| wallets.remove(wallet_id))
+            .collect())
+    }
+
+    async fn get_balances(&self, wallet_ids: &[i32]) -> anyhow::Result<HashMap<i32, Vec<Balance>>> {
+        let rows = self
+            .find::<WalletsToTokens>()
+            .column_as(tokens::Column::Token, "token")
+            .filter(wallets_to_tokens::Column::WalletId.is_in(wallet_ids.iter().copied()))
+            .join(
+                JoinType::InnerJoin,
+                wallets_to_tokens::Relation::Tokens.def(),
+            )
+            .order_by_asc(wallets_to_tokens::Column::TokenId)
+            .into_model::<BalanceRow>()
+            .all(&self.db)
+            .await?;
+
+        let mut balances: HashMap<i32, Vec<Balance>> = HashMap::new();
+        for row in rows {
+            balances.entry(row.wallet_id).or_default().push(Balance {
+                token_id: row.token_id,
+                token: row.token,
+                volume: row.volume,
+            });
+        }
+
+        Ok(balances)
+    }
+
+    /// Move `amount` units of a token between two wallets, opening a balance
+    /// for the receiving wallet if it does not hold the token yet, and record
+    /// the move in both wallets' history.
+    pub(super) async fn move_volume(
+        &self,
+        from_wallet_id: i32,
+        to_wallet_id: i32,
+        token_id: i32,
+        amount: i32,
+        ledger_transaction_id: i32,
+        tx: &DatabaseTransaction,
+    ) -> Result<(), TxError> {
+        let mut from_wallet = self
+            .find_by_id::<WalletsToTokens, _>((from_wallet_id, token_id))
+            .one(tx)
+            .await?
+            .ok_or_else(|| {
+                RepoError::InsufficientFunds(format!("wallet {from_wallet_id} holds no such token"))
+            })?
+            .into_active_model();
+
+        let from_wallet_vol = from_wallet.volume.clone().unwrap();
+        if from_wallet_vol < amount {
+            return Err(RepoError::InsufficientFunds(format!(
+                "insufficient volume of wallet {from_wallet_id}"
+            ))
+            .into());
+        }
+        from_wallet.volume = Set(from_wallet_vol - amount);
+        let _ = from_wallet.update(tx).await?;
+
+        match self
+            .find_by_id::<WalletsToTokens, _>((to_wallet_id, token_id))
+            .one(tx)
+            .await?
+        {
+            Some(to_wallet) => {
+                let to_wallet_vol = to_wallet.volume;
+                let mut to_wallet = to_wallet.into_active_model();
+                to_wallet.volume = Set(to_wallet_vol + amount);
+                let _ = to_wallet.update(tx).await?;
+            }
+            None => {
+                let _ = self
+                    .create_wallet_to_token(to_wallet_id, token_id, amount, tx)
+                    .await?;
+            }
+        }
+
+        self.record_balance_change(ledger_transaction_id, from_wallet_id, token_id, -amount, tx)
+            .await?;
+        self.record_balance_change(ledger_transaction_id, to_wallet_id, token_id, amount, tx)
+            .await?;
+        Ok(())
+    }
+}
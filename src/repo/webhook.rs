@@ -0,0 +1,449 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use reqwest::Client;
+use sea_orm::{
+    sea_query::{Expr, LockBehavior, LockType, OnConflict},
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, QuerySelect,
+    TransactionTrait,
+};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::task::JoinHandle;
+
+use super::{EdgeWallet, Repo, RepoError};
+use crate::{
+    entity::{prelude::*, sea_orm_active_enums::WebhookDeliveryState, *},
+    events::{EventRecord, EventSink, EventType},
+};
+
+/// Prefix of every webhook signing secret, to tell them apart from other
+/// secrets.
+const SECRET_PREFIX: &str = "whsec_";
+
+/// Deliveries attempted per dispatcher round.
+const DISPATCH_BATCH: u64 = 100;
+
+/// How long a dispatcher has to attempt the deliveries it claimed before
+/// another one may claim them, well beyond the client's request timeout.
+pub const DELIVERY_LEASE: Duration = Duration::from_secs(300);
+
+/// `type` of the event delivered.
+pub const EVENT_HEADER: &str = "x-bc-orm-event";
+/// Id of the delivery, the same on every retry.
+pub const DELIVERY_HEADER: &str = "x-bc-orm-delivery";
+/// Unix time the request was signed at.
+pub const TIMESTAMP_HEADER: &str = "x-bc-orm-timestamp";
+/// `sha256=` followed by the hex HMAC of the request, see [`sign_webhook`].
+pub const SIGNATURE_HEADER: &str = "x-bc-orm-signature";
+
+#[derive(Debug)]
+pub struct NewWebhook {
+    pub url: String,
+    /// Events to deliver, every event when empty.
+    pub event_types: Vec<EventType>,
+}
+
+/// A newly created subscription, with the signing secret shown only once.
+#[derive(Serialize, Debug)]
+pub struct IssuedWebhook {
+    #[serde(flatten)]
+    pub webhook: webhook_subscriptions::Model,
+    pub secret: String,
+}
+
+/// How failed deliveries are retried: the `n`th retry waits `base_delay`
+/// doubled `n - 1` times, up to `max_delay`, and a delivery failing
+/// `max_attempts` times goes to the dead letters.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: i32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 8,
+            base_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before the attempt following `attempts` failed ones.
+    pub fn delay(&self, attempts: i32) -> Duration {
+        let doublings = attempts.saturating_sub(1).clamp(0, 31) as u32;
+        self.base_delay
+            .saturating_mul(2u32.pow(doublings))
+            .min(self.max_delay)
+    }
+}
+
+/// Body of a webhook request: the event, and the edge it is about as it was
+/// when the delivery was queued.
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    #[serde(flatten)]
+    event: &'a EventRecord,
+    edge_wallet: Option<EdgeWallet>,
+}
+
+/// Signature of a webhook request: `sha256=` followed by the hex HMAC-SHA256,
+/// keyed with the subscription secret, of the timestamp, a dot and the body.
+pub fn sign_webhook(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+impl webhook_subscriptions::Model {
+    /// Whether events of `event_type` are delivered to this subscription.
+    pub fn accepts(&self, event_type: EventType) -> bool {
+        let event_types: Vec<EventType> =
+            serde_json::from_value(self.event_types.clone()).unwrap_or_default();
+        event_types.is_empty() || event_types.contains(&event_type)
+    }
+}
+
+impl Repo {
+    pub async fn create_webhook(&self, data: NewWebhook) -> anyhow::Result<IssuedWebhook> {
+        let url = reqwest::Url::parse(&data.url)
+            .map_err(|e| anyhow::anyhow!(RepoError::Invalid(format!("webhook url: {e}"))))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            anyhow::bail!(RepoError::Invalid(
+                "webhook url is not an http(s) URL".to_string()
+            ));
+        }
+
+        let mut event_types = data.event_types;
+        event_types.sort_by_key(|event_type| event_type.as_str());
+        event_types.dedup();
+
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let secret = format!("{SECRET_PREFIX}{}", hex::encode(secret));
+
+        let webhook = webhook_subscriptions::ActiveModel {
+            tenant_id: Set(self.tenant_id),
+            url: Set(url.to_string()),
+            secret: Set(secret.clone()),
+            event_types: Set(serde_json::to_value(event_types)?),
+            ..Default::default()
+        }
+        .insert(&self.db)
+        .await?;
+
+        Ok(IssuedWebhook { webhook, secret })
+    }
+
+    pub async fn list_webhooks(&self) -> anyhow::Result<Vec<webhook_subscriptions::Model>> {
+        Ok(self
+            .find::<WebhookSubscriptions>()
+            .order_by_asc(webhook_subscriptions::Column::Id)
+            .all(&self.db)
+            .await?)
+    }
+
+    /// Delete a subscription along with its deliveries.
+    pub async fn delete_webhook(&self, id: i32) -> anyhow::Result<webhook_subscriptions::Model> {
+        let webhook = self
+            .find_by_id::<WebhookSubscriptions, _>(id)
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!(RepoError::NotFound("webhook not found".to_string())))?;
+        WebhookSubscriptions::delete_by_id(webhook.id)
+            .exec(&self.db)
+            .await?;
+        Ok(webhook)
+    }
+
+    /// Queue a delivery of `event` to every subscription of this repo's
+    /// tenant accepting it. Queuing the same event again is a no-op, so this
+    /// can sit behind the at-least-once outbox relay. Returns how many
+    /// subscriptions accept the event.
+    pub async fn enqueue_webhooks(&self, event: &EventRecord) -> anyhow::Result<usize> {
+        let event_type = event.event.event_type();
+        let subscriptions = self
+            .find::<WebhookSubscriptions>()
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .filter(|subscription| subscription.accepts(event_type))
+            .collect::<Vec<_>>();
+        if subscriptions.is_empty() {
+            return Ok(0);
+        }
+
+        let edge_wallet = match event.event.edge_id() {
+            Some(edge_id) => self.get_edge_wallets(&[edge_id]).await?.remove(&edge_id),
+            None => None,
+        };
+        let payload = serde_json::to_value(WebhookPayload { event, edge_wallet })?;
+
+        let now = chrono::Utc::now().fixed_offset();
+        let deliveries = subscriptions
+            .iter()
+            .map(|subscription| webhook_deliveries::ActiveModel {
+                tenant_id: Set(self.tenant_id),
+                subscription_id: Set(subscription.id),
+                event_id: Set(event.id),
+                event_type: Set(event_type.to_string()),
+                payload: Set(payload.clone()),
+                state: Set(WebhookDeliveryState::Pending),
+                attempts: Set(0),
+                next_attempt_at: Set(now),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        WebhookDeliveries::insert_many(deliveries)
+            .on_conflict(
+                OnConflict::columns([
+                    webhook_deliveries::Column::SubscriptionId,
+                    webhook_deliveries::Column::EventId,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(&self.db)
+            .await?;
+
+        Ok(subscriptions.len())
+    }
+
+    /// Attempt up to `limit` due deliveries of every tenant, oldest first.
+    /// A delivery succeeds on any 2xx response; a failed one is retried
+    /// according to `policy`. Returns how many deliveries were attempted.
+    ///
+    /// The deliveries are claimed for [`DELIVERY_LEASE`] in a transaction of
+    /// their own and sent outside of it, so a slow endpoint holds no locks.
+    /// Should this dispatcher die before recording the results, they are
+    /// attempted again once the lease runs out.
+    pub async fn deliver_webhooks(
+        &self,
+        client: &Client,
+        policy: &RetryPolicy,
+        limit: u64,
+    ) -> anyhow::Result<usize> {
+        let due = self.claim_deliveries(limit).await?;
+        let subscriptions = WebhookSubscriptions::find()
+            .filter(
+                webhook_subscriptions::Column::Id
+                    .is_in(due.iter().map(|delivery| delivery.subscription_id)),
+            )
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|subscription| (subscription.id, subscription))
+            .collect::<HashMap<_, _>>();
+
+        let attempted = due.len();
+        let attempts = due.into_iter().filter_map(|delivery| {
+            // deliveries of a deleted subscription go with it
+            let subscription = subscriptions.get(&delivery.subscription_id)?;
+            Some(async move {
+                let result = send_webhook(client, subscription, &delivery).await;
+                (delivery, result)
+            })
+        });
+        for (delivery, result) in futures::future::join_all(attempts).await {
+            self.record_delivery(policy, delivery, result).await?;
+        }
+
+        Ok(attempted)
+    }
+
+    /// Take up to `limit` due deliveries off the queue for [`DELIVERY_LEASE`].
+    async fn claim_deliveries(&self, limit: u64) -> anyhow::Result<Vec<webhook_deliveries::Model>> {
+        let tx = self.db.begin().await?;
+        let now = chrono::Utc::now().fixed_offset();
+        // skip deliveries another dispatcher is claiming
+        let due = WebhookDeliveries::find()
+            .filter(webhook_deliveries::Column::State.eq(WebhookDeliveryState::Pending))
+            .filter(webhook_deliveries::Column::NextAttemptAt.lte(now))
+            .order_by_asc(webhook_deliveries::Column::NextAttemptAt)
+            .order_by_asc(webhook_deliveries::Column::Id)
+            .limit(limit)
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+            .all(&tx)
+            .await?;
+        if !due.is_empty() {
+            WebhookDeliveries::update_many()
+                .col_expr(
+                    webhook_deliveries::Column::NextAttemptAt,
+                    Expr::value(now + chrono::Duration::from_std(DELIVERY_LEASE)?),
+                )
+                .filter(
+                    webhook_deliveries::Column::Id.is_in(due.iter().map(|delivery| delivery.id)),
+                )
+                .exec(&tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(due)
+    }
+
+    /// Record the outcome of an attempt at a claimed delivery, unless another
+    /// dispatcher recorded one first after the lease ran out.
+    async fn record_delivery(
+        &self,
+        policy: &RetryPolicy,
+        delivery: webhook_deliveries::Model,
+        result: anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let now = chrono::Utc::now().fixed_offset();
+        let attempts = delivery.attempts + 1;
+        let mut update = webhook_deliveries::ActiveModel {
+            attempts: Set(attempts),
+            updated_at: Set(now),
+            ..Default::default()
+        };
+        match result {
+            Ok(()) => {
+                update.state = Set(WebhookDeliveryState::Delivered);
+                update.delivered_at = Set(Some(now));
+                update.last_error = Set(None);
+            }
+            Err(e) => {
+                if attempts >= policy.max_attempts {
+                    update.state = Set(WebhookDeliveryState::Dead);
+                } else {
+                    let delay = chrono::Duration::from_std(policy.delay(attempts))?;
+                    update.next_attempt_at = Set(now + delay);
+                }
+                update.last_error = Set(Some(e.to_string()));
+            }
+        }
+        WebhookDeliveries::update_many()
+            .set(update)
+            .filter(webhook_deliveries::Column::Id.eq(delivery.id))
+            .filter(webhook_deliveries::Column::State.eq(WebhookDeliveryState::Pending))
+            .filter(webhook_deliveries::Column::Attempts.eq(delivery.attempts))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// Deliveries of this repo's tenant that failed every attempt.
+    pub async fn list_dead_letters(&self) -> anyhow::Result<Vec<webhook_deliveries::Model>> {
+        Ok(self
+            .find::<WebhookDeliveries>()
+            .filter(webhook_deliveries::Column::State.eq(WebhookDeliveryState::Dead))
+            .order_by_asc(webhook_deliveries::Column::Id)
+            .all(&self.db)
+            .await?)
+    }
+
+    /// Queue a dead delivery again, with a fresh set of attempts.
+    pub async fn redeliver_webhook(
+        &self,
+        delivery_id: i32,
+    ) -> anyhow::Result<webhook_deliveries::Model> {
+        let delivery = self
+            .find_by_id::<WebhookDeliveries, _>(delivery_id)
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!(RepoError::NotFound("delivery not found".to_string()))
+            })?;
+        if delivery.state != WebhookDeliveryState::Dead {
+            anyhow::bail!(RepoError::Conflict(
+                "only dead deliveries can be redelivered".to_string()
+            ));
+        }
+
+        let mut delivery = delivery.into_active_model();
+        delivery.state = Set(WebhookDeliveryState::Pending);
+        delivery.attempts = Set(0);
+        delivery.next_attempt_at = Set(chrono::Utc::now().fixed_offset());
+        Ok(delivery.update(&self.db).await?)
+    }
+
+    /// Spawn a background task attempting due deliveries every `period`.
+    pub fn spawn_webhook_dispatcher(
+        self: Arc<Self>,
+        client: Client,
+        policy: RetryPolicy,
+        period: Duration,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                loop {
+                    match self
+                        .deliver_webhooks(&client, &policy, DISPATCH_BATCH)
+                        .await
+                    {
+                        Ok(attempted) if attempted as u64 == DISPATCH_BATCH => continue,
+                        Ok(_) => break,
+                        Err(e) => {
+                            log::error!("webhook dispatcher error: {e:?}");
+                            break;
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+async fn send_webhook(
+    client: &Client,
+    subscription: &webhook_subscriptions::Model,
+    delivery: &webhook_deliveries::Model,
+) -> anyhow::Result<()> {
+    let body = serde_json::to_vec(&delivery.payload)?;
+    let timestamp = chrono::Utc::now().timestamp();
+    let response = client
+        .post(&subscription.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, &delivery.event_type)
+        .header(DELIVERY_HEADER, delivery.id)
+        .header(TIMESTAMP_HEADER, timestamp)
+        .header(
+            SIGNATURE_HEADER,
+            sign_webhook(&subscription.secret, timestamp, &body),
+        )
+        .body(body)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        anyhow::bail!("HTTP {}", response.status());
+    }
+    Ok(())
+}
+
+/// Queues webhook deliveries for the events relayed from the outbox, in the
+/// tenant of each event.
+pub struct WebhookSink {
+    repo: Arc<Repo>,
+}
+
+impl WebhookSink {
+    pub fn new(repo: Arc<Repo>) -> Self {
+        WebhookSink { repo }
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    async fn publish(&self, event: &EventRecord) -> anyhow::Result<()> {
+        self.repo
+            .for_tenant(event.tenant_id)
+            .enqueue_webhooks(event)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use std::sync::Arc;
+
+use bc_orm::{
+    auth::AuthorizedRepo,
+    http,
+    repo::{
+        ApiClient, ListEdges, ListTokens, ListWallets, ProvisionWallet, Repo, RepoError, Scope,
+        TransferToken,
+    },
+};
+use reqwest::StatusCode;
+
+const TENANT: i32 = 1;
+
+/// Funded edges 10 and 20 of `TENANT`, see [`common::seed`].
+async fn setup() -> Arc<Repo> {
+    let db = common::database().await;
+    common::seed(&db, TENANT, 10, true).await;
+    common::seed(&db, TENANT, 20, true).await;
+    common::repo(db)
+}
+
+fn client(scopes: &[Scope], edge_ids: Option<Vec<i32>>) -> ApiClient {
+    ApiClient {
+        id: 1,
+        tenant_id: TENANT,
+        name: "client".to_string(),
+        scopes: scopes.to_vec(),
+        edge_ids,
+    }
+}
+
+fn is_forbidden(e: &anyhow::Error) -> bool {
+    e.chain()
+        .any(|cause| matches!(cause.downcast_ref(), Some(RepoError::Forbidden(_))))
+}
+
+#[tokio::test]
+async fn missing_or_invalid_keys_are_unauthorized() {
+    let repo = setup().await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(http::serve(repo, listener, std::future::pending()));
+    let client = reqwest::Client::new();
+
+    let health = client.get(format!("{url}/health")).send().await.unwrap();
+    assert_eq!(health.status(), StatusCode::OK);
+
+    let missing = client.get(format!("{url}/edges")).send().await.unwrap();
+    assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+    for authorization in ["Basic dXNlcjpwYXNz", "Bearer ", "Bearer unknown-key"] {
+        let response = client
+            .get(format!("{url}/edges/10"))
+            .header("authorization", authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.status(),
+            StatusCode::UNAUTHORIZED,
+            "{authorization}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn clients_need_the_scope_of_each_operation() {
+    let repo = setup().await;
+    let reader = AuthorizedRepo::new(&repo, client(&[Scope::Read], None));
+
+    assert_eq!(reader.get_edge_wallet(10).await.unwrap().edge_id, 10);
+    let e = reader
+        .transfer_token(TransferToken { edge_id: 10 })
+        .await
+        .unwrap_err();
+    assert!(is_forbidden(&e), "{e:#}");
+    let e = reader
+        .provision_wallet(ProvisionWallet {
+            edge_id: 30,
+            asset: serde_json::json!({}),
+        })
+        .await
+        .unwrap_err();
+    assert!(is_forbidden(&e), "{e:#}");
+    let e = reader.deprovision_edge(10).await.unwrap_err();
+    assert!(is_forbidden(&e), "{e:#}");
+    let e = reader.release_escrow(10).await.unwrap_err();
+    assert!(is_forbidden(&e), "{e:#}");
+
+    let transferer = AuthorizedRepo::new(&repo, client(&[Scope::Transfer], None));
+    let e = transferer.get_edge_wallet(10).await.unwrap_err();
+    assert!(is_forbidden(&e), "{e:#}");
+}
+
+#[tokio::test]
+async fn restricted_clients_only_reach_their_edges() {
+    let repo = setup().await;
+    let restricted = AuthorizedRepo::new(
+        &repo,
+        client(&[Scope::Read, Scope::Transfer], Some(vec![10])),
+    );
+
+    assert_eq!(restricted.get_edge_wallet(10).await.unwrap().edge_id, 10);
+    let e = restricted.get_edge_wallet(20).await.unwrap_err();
+    assert!(is_forbidden(&e), "{e:#}");
+    let e = restricted
+        .transfer_token(TransferToken { edge_id: 20 })
+        .await
+        .unwrap_err();
+    assert!(is_forbidden(&e), "{e:#}");
+    // edge 20's src wallet and escrow
+    let e = restricted.get_wallet_balances(21).await.unwrap_err();
+    assert!(is_forbidden(&e), "{e:#}");
+    let e = restricted.refund_escrow(20).await.unwrap_err();
+    assert!(is_forbidden(&e), "{e:#}");
+
+    let edges = restricted
+        .list_edges(ListEdges::default())
+        .await
+        .unwrap()
+        .items;
+    assert_eq!(
+        edges.iter().map(|edge| edge.edge_id).collect::<Vec<_>>(),
+        [10]
+    );
+    let asking_for_both = ListEdges {
+        edge_ids: Some(vec![10, 20]),
+        ..Default::default()
+    };
+    let edges = restricted.list_edges(asking_for_both).await.unwrap().items;
+    assert_eq!(
+        edges.iter().map(|edge| edge.edge_id).collect::<Vec<_>>(),
+        [10]
+    );
+}
+
+#[tokio::test]
+async fn restricted_clients_cannot_list_wallets_or_tokens() {
+    let repo = setup().await;
+    let restricted = AuthorizedRepo::new(&repo, client(&[Scope::Read], Some(vec![10])));
+    let e = restricted
+        .list_wallets(ListWallets::default())
+        .await
+        .unwrap_err();
+    assert!(is_forbidden(&e), "{e:#}");
+    let e = restricted
+        .list_tokens(ListTokens::default())
+        .await
+        .unwrap_err();
+    assert!(is_forbidden(&e), "{e:#}");
+
+    let unrestricted = AuthorizedRepo::new(&repo, client(&[Scope::Read], None));
+    let wallets = unrestricted
+        .list_wallets(ListWallets::default())
+        .await
+        .unwrap();
+    assert_eq!(wallets.items.len(), 8);
+    let tokens = unrestricted
+        .list_tokens(ListTokens::default())
+        .await
+        .unwrap();
+    assert_eq!(tokens.items.len(), 4);
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use bc_orm::repo::{BatchTransfer, Payout, ProvisionWallet, TransferToken};
+
+#[tokio::test]
+async fn wallets_hold_and_move_several_tokens() {
+    let repo = common::repo(common::database().await);
+    let mut edges = Vec::new();
+    for edge_id in [1, 2] {
+        let edge_wallet = repo
+            .clone()
+            .provision_wallet(ProvisionWallet {
+                edge_id,
+                asset: serde_json::json!({ "edge": edge_id }),
+            })
+            .await
+            .unwrap();
+        edges.push(edge_wallet);
+    }
+    let (one, two) = (&edges[0], &edges[1]);
+
+    // edge 1's src wallet receives some of edge 2's FT
+    repo.clone()
+        .batch_transfer(BatchTransfer {
+            from_wallet_id: two.src_wallet.wallet_id,
+            token: two.token.clone(),
+            payouts: vec![Payout {
+                to_wallet_id: one.src_wallet.wallet_id,
+                amount: 30,
+            }],
+        })
+        .await
+        .unwrap();
+    let mut balances = repo
+        .get_wallet_balances(one.src_wallet.wallet_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|balance| (balance.token, balance.volume))
+        .collect::<Vec<_>>();
+    balances.sort();
+    let mut expected = vec![(one.token.clone(), 100), (two.token.clone(), 30)];
+    expected.sort();
+    assert_eq!(balances, expected);
+
+    // an edge transfer only moves the edge's own FT
+    let edge_wallet = repo
+        .clone()
+        .transfer_token(TransferToken { edge_id: 1 })
+        .await
+        .unwrap();
+    assert_eq!(edge_wallet.src_wallet.volume(&one.token), 99);
+    assert_eq!(edge_wallet.src_wallet.volume(&two.token), 30);
+    assert_eq!(edge_wallet.dst_wallet.volume(&one.token), 1);
+    assert_eq!(edge_wallet.dst_wallet.volume(&two.token), 0);
+
+    // and the other token can be paid on
+    let wallets = repo
+        .clone()
+        .batch_transfer(BatchTransfer {
+            from_wallet_id: one.src_wallet.wallet_id,
+            token: two.token.clone(),
+            payouts: vec![Payout {
+                to_wallet_id: one.dst_wallet.wallet_id,
+                amount: 10,
+            }],
+        })
+        .await
+        .unwrap();
+    assert_eq!(wallets[0].volume(&one.token), 99);
+    assert_eq!(wallets[0].volume(&two.token), 20);
+    assert_eq!(wallets[1].volume(&one.token), 1);
+    assert_eq!(wallets[1].volume(&two.token), 10);
+
+    assert!(repo.reconcile_edges(&[1, 2]).await.unwrap().is_empty());
+}
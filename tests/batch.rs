@@ -0,0 +1,138 @@
+mod common;
+
+use std::sync::Arc;
+
+use bc_orm::repo::{BatchTransfer, EdgeWallet, Payout, ProvisionWallet, Repo, RepoError};
+
+/// Edges 1, 2 and 3 on the in-memory ledger.
+async fn setup() -> (Arc<Repo>, Vec<EdgeWallet>) {
+    let repo = common::repo(common::database().await);
+    let mut edges = Vec::new();
+    for edge_id in 1..=3 {
+        let edge_wallet = repo
+            .clone()
+            .provision_wallet(ProvisionWallet {
+                edge_id,
+                asset: serde_json::json!({ "edge": edge_id }),
+            })
+            .await
+            .unwrap();
+        edges.push(edge_wallet);
+    }
+    (repo, edges)
+}
+
+fn payouts(payouts: &[(i32, i32)]) -> Vec<Payout> {
+    payouts
+        .iter()
+        .map(|&(to_wallet_id, amount)| Payout {
+            to_wallet_id,
+            amount,
+        })
+        .collect()
+}
+
+/// Height of the last block of the in-memory ledger, one per transaction.
+async fn height(repo: &Repo) -> i64 {
+    let mut height = 0;
+    while repo.ledger.get_block(height + 1).await.unwrap().is_some() {
+        height += 1;
+    }
+    height
+}
+
+#[tokio::test]
+async fn invalid_batches_are_refused_before_the_ledger() {
+    let (repo, edges) = setup().await;
+    let from = &edges[0];
+    let to = edges[1].dst_wallet.wallet_id;
+    let before = height(&repo).await;
+
+    let batch = |token: &str, to: &[(i32, i32)]| {
+        repo.clone().batch_transfer(BatchTransfer {
+            from_wallet_id: from.src_wallet.wallet_id,
+            token: token.to_string(),
+            payouts: payouts(to),
+        })
+    };
+    let refusal = |e: anyhow::Error| e.downcast::<RepoError>().unwrap();
+
+    for invalid in [
+        &[][..],
+        &[(to, 0)],
+        &[(to, -1)],
+        &[(from.src_wallet.wallet_id, 1)],
+        &[(to, i32::MAX), (to, 1)],
+    ] {
+        let e = batch(&from.token, invalid).await.unwrap_err();
+        assert!(matches!(refusal(e), RepoError::Invalid(_)), "{invalid:?}");
+    }
+    let e = batch(&from.token, &[(to, 1), (999, 1)]).await.unwrap_err();
+    assert!(matches!(refusal(e), RepoError::NotFound(_)));
+    let e = batch(&from.token, &[(to, 60), (to, 41)]).await.unwrap_err();
+    assert!(matches!(refusal(e), RepoError::InsufficientFunds(_)));
+    // a token the sender does not hold
+    let e = batch(&edges[1].token, &[(to, 1)]).await.unwrap_err();
+    assert!(matches!(refusal(e), RepoError::InsufficientFunds(_)));
+
+    assert_eq!(height(&repo).await, before);
+    let edge_wallet = repo.get_edge_wallet(1).await.unwrap();
+    assert_eq!(edge_wallet.src_wallet.volume(&edge_wallet.token), 100);
+}
+
+#[tokio::test]
+async fn batches_pay_out_in_one_transaction() {
+    let (repo, edges) = setup().await;
+    let from = &edges[0];
+    let before = height(&repo).await;
+
+    let wallets = repo
+        .clone()
+        .batch_transfer(BatchTransfer {
+            from_wallet_id: from.src_wallet.wallet_id,
+            token: from.token.clone(),
+            payouts: payouts(&[
+                (edges[1].dst_wallet.wallet_id, 10),
+                (edges[2].dst_wallet.wallet_id, 20),
+                (from.dst_wallet.wallet_id, 30),
+            ]),
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        wallets
+            .iter()
+            .map(|wallet| (wallet.wallet_id, wallet.volume(&from.token)))
+            .collect::<Vec<_>>(),
+        [
+            (from.src_wallet.wallet_id, 40),
+            (edges[1].dst_wallet.wallet_id, 10),
+            (edges[2].dst_wallet.wallet_id, 20),
+            (from.dst_wallet.wallet_id, 30),
+        ]
+    );
+
+    // one TRANSFER paying every recipient and the change back
+    assert_eq!(height(&repo).await, before + 1);
+    let block = repo.ledger.get_block(before + 1).await.unwrap().unwrap();
+    let tx = &block.transactions[0];
+    assert_eq!(tx.operation, "TRANSFER");
+    let mut outputs = tx
+        .outputs
+        .iter()
+        .map(|output| (output.public_keys[0].clone(), output.amount.clone()))
+        .collect::<Vec<_>>();
+    outputs.sort();
+    let mut expected = [
+        (from.src_wallet.public_key.clone(), "40"),
+        (edges[1].dst_wallet.public_key.clone(), "10"),
+        (edges[2].dst_wallet.public_key.clone(), "20"),
+        (from.dst_wallet.public_key.clone(), "30"),
+    ]
+    .map(|(public_key, amount)| (public_key, amount.to_string()));
+    expected.sort();
+    assert_eq!(outputs, expected);
+
+    assert!(repo.reconcile_edges(&[1, 2, 3]).await.unwrap().is_empty());
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use std::sync::Arc;
+
+use bc_orm::{
+    cache::{CacheStats, EdgeCache},
+    repo::{BatchTransfer, Payout, ProvisionWallet, Repo, TransferToken},
+};
+
+/// Edges 1 and 2 on the in-memory ledger, behind an in-memory cache.
+async fn setup() -> Arc<Repo> {
+    let mut repo = Arc::into_inner(common::repo(common::database().await)).unwrap();
+    repo.cache = Some(Arc::new(EdgeCache::in_memory()));
+    let repo = Arc::new(repo);
+    for edge_id in [1, 2] {
+        repo.clone()
+            .provision_wallet(ProvisionWallet {
+                edge_id,
+                asset: serde_json::json!({}),
+            })
+            .await
+            .unwrap();
+    }
+    repo
+}
+
+/// Hits and misses since `before`.
+fn since(repo: &Repo, before: CacheStats) -> (u64, u64) {
+    let stats = repo.cache_stats().unwrap();
+    (stats.hits - before.hits, stats.misses - before.misses)
+}
+
+#[tokio::test]
+async fn reads_go_through_the_cache() {
+    let repo = setup().await;
+    let before = repo.cache_stats().unwrap();
+
+    repo.get_edge_wallet(1).await.unwrap();
+    repo.get_edge_wallet(1).await.unwrap();
+    repo.get_edge_wallets(&[1, 2]).await.unwrap();
+    // both were put by their provisioning
+    assert_eq!(since(&repo, before), (4, 0));
+
+    // another tenant's edge 1 is a different entry
+    assert!(repo.for_tenant(7).get_edge_wallet(1).await.is_err());
+    assert_eq!(since(&repo, before), (4, 1));
+}
+
+#[tokio::test]
+async fn mutations_invalidate_what_they_touch() {
+    let repo = setup().await;
+    let edge_wallet = repo.get_edge_wallet(1).await.unwrap();
+    repo.get_edge_wallet(2).await.unwrap();
+
+    let transferred = repo
+        .clone()
+        .transfer_token(TransferToken { edge_id: 1 })
+        .await
+        .unwrap();
+    assert_eq!(transferred.src_wallet.volume(&edge_wallet.token), 99);
+    let before = repo.cache_stats().unwrap();
+    let cached = repo.get_edge_wallet(1).await.unwrap();
+    assert_eq!(cached.src_wallet.volume(&edge_wallet.token), 99);
+    repo.get_edge_wallet(2).await.unwrap();
+    assert_eq!(since(&repo, before), (2, 0));
+
+    // a batch may pay any edge's wallets, so every edge is dropped
+    repo.clone()
+        .batch_transfer(BatchTransfer {
+            from_wallet_id: edge_wallet.src_wallet.wallet_id,
+            token: edge_wallet.token.clone(),
+            payouts: vec![Payout {
+                to_wallet_id: edge_wallet.dst_wallet.wallet_id,
+                amount: 9,
+            }],
+        })
+        .await
+        .unwrap();
+    let before = repo.cache_stats().unwrap();
+    let edge_wallet = repo.get_edge_wallet(1).await.unwrap();
+    assert_eq!(edge_wallet.dst_wallet.volume(&edge_wallet.token), 10);
+    repo.get_edge_wallet(2).await.unwrap();
+    assert_eq!(since(&repo, before), (0, 2));
+
+    let closed = repo.clone().deprovision_edge(1).await.unwrap();
+    assert!(closed.closed_at.is_some());
+    assert!(repo.get_edge_wallet(1).await.unwrap().closed_at.is_some());
+}
@@ -0,0 +1,223 @@
+//! Fixtures shared by the integration tests: an in-memory SQLite database
+//! with the schema of the entities (or a migrated Postgres one, where
+//! available), seeded edges, and a repo over an in-memory ledger. The
+//! seeded wallets have no outputs on that ledger, so any attempt to move
+//! their tokens fails loudly.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use bc_orm::{
+    entity::{prelude::*, sea_orm_active_enums::*, *},
+    ledger::{InMemoryLedger, Ledger},
+    repo::{Repo, DEFAULT_TENANT_ID},
+    sea_query::Index,
+    ActiveModelBehavior, ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, DbConfig,
+    EntityTrait, IntoActiveModel, Schema,
+};
+
+pub async fn database() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let backend = db.get_database_backend();
+    for table in [
+        schema.create_table_from_entity(Wallets),
+        schema.create_table_from_entity(Tokens),
+        schema.create_table_from_entity(WalletsToTokens),
+        schema.create_table_from_entity(EdgesToWallets),
+        schema.create_table_from_entity(Escrows),
+        schema.create_table_from_entity(OutboxEvents),
+        schema.create_table_from_entity(WebhookSubscriptions),
+        schema.create_table_from_entity(WebhookDeliveries),
+        schema.create_table_from_entity(LedgerTransactions),
+        schema.create_table_from_entity(BalanceChanges),
+        schema.create_table_from_entity(LedgerCursors),
+    ] {
+        db.execute(backend.build(&table)).await.unwrap();
+    }
+    // what the migration adds on top of the entities
+    let deliveries = Index::create()
+        .name("idx_webhook_deliveries_subscription_id_event_id")
+        .table(WebhookDeliveries)
+        .col(webhook_deliveries::Column::SubscriptionId)
+        .col(webhook_deliveries::Column::EventId)
+        .unique()
+        .to_owned();
+    db.execute(backend.build(&deliveries)).await.unwrap();
+    let edges = Index::create()
+        .name("idx_edges_to_wallets_tenant_id_edge_id")
+        .table(EdgesToWallets)
+        .col(edges_to_wallets::Column::TenantId)
+        .col(edges_to_wallets::Column::EdgeId)
+        .unique()
+        .to_owned();
+    db.execute(backend.build(&edges)).await.unwrap();
+    // SQLite has no arrays, so API clients can be looked up but not stored:
+    // build authenticated ones with `AuthorizedRepo::new`
+    db.execute_unprepared(
+        "CREATE TABLE api_clients (
+            id integer PRIMARY KEY AUTOINCREMENT,
+            name text NOT NULL UNIQUE,
+            key_hash text NOT NULL UNIQUE,
+            scopes text NOT NULL,
+            edge_ids text,
+            revoked_at text,
+            created_at text NOT NULL,
+            updated_at text NOT NULL,
+            tenant_id integer NOT NULL
+        )",
+    )
+    .await
+    .unwrap();
+    db
+}
+
+/// A fresh schema of the Postgres database named by `TEST_DATABASE_URL`,
+/// migrated, for what SQLite cannot stand in for: array columns, so API
+/// clients and authenticated calls, and `SKIP LOCKED`. `None` when the
+/// variable is not set, and the test should then return early.
+pub async fn postgres() -> Option<DatabaseConnection> {
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL is not set, skipping");
+        return None;
+    };
+    let schema = format!("test_{:08x}", rand::random::<u32>());
+    Database::connect(&url)
+        .await
+        .unwrap()
+        .execute_unprepared(&format!("CREATE SCHEMA {schema}"))
+        .await
+        .unwrap();
+    let config = DbConfig {
+        schema: Some(schema),
+        ..DbConfig::new(url)
+    };
+    Some(bc_orm::connect_with(&config).await.unwrap())
+}
+
+pub fn repo(db: DatabaseConnection) -> Arc<Repo> {
+    let ledger = InMemoryLedger::new();
+    let treasury = ledger.generate_keypair();
+    Arc::new(Repo {
+        db,
+        ledger: Arc::new(ledger),
+        ft_supply: 100,
+        mint_metadata: serde_json::Value::Null,
+        treasury_public_key: Some(treasury.public_key),
+        cache: None,
+        tenant_id: DEFAULT_TENANT_ID,
+    })
+}
+
+/// Edge `id` of `tenant`: wallets `id + 1` (src), `id + 2` (dst), `id + 3`
+/// (NFT) and `id + 4` (escrow), FT `id + 1` and NFT `id + 2`. When `funded`,
+/// the src wallet holds 95 FT units, the NFT wallet the NFT and an open
+/// escrow `id` holds 5 units; otherwise every balance is empty, so the edge
+/// can be deprovisioned without touching the ledger.
+pub async fn seed(db: &DatabaseConnection, tenant_id: i32, id: i32, funded: bool) {
+    let now = chrono::Utc::now().fixed_offset();
+
+    for wallet_id in id + 1..=id + 4 {
+        insert(
+            db,
+            wallets::Model {
+                id: wallet_id,
+                public_key: format!("public-{wallet_id}"),
+                private_key: format!("private-{wallet_id}"),
+                closed_at: None,
+                created_at: now,
+                updated_at: now,
+                tenant_id,
+            }
+            .into_active_model(),
+        )
+        .await;
+    }
+    for (token_id, kind) in [
+        (id + 1, TokenKind::Fungible),
+        (id + 2, TokenKind::NonFungible),
+    ] {
+        insert(
+            db,
+            tokens::Model {
+                id: token_id,
+                token: format!("token-{token_id}"),
+                asset: None,
+                metadata: None,
+                supply: None,
+                kind: Some(kind),
+                creator_wallet_id: Some(id + 1),
+                created_at: now,
+                updated_at: now,
+                tenant_id,
+            }
+            .into_active_model(),
+        )
+        .await;
+    }
+    let volumes = match funded {
+        true => [95, 1, 5],
+        false => [0, 0, 0],
+    };
+    for ((wallet_id, token_id), volume) in [(id + 1, id + 1), (id + 3, id + 2), (id + 4, id + 1)]
+        .into_iter()
+        .zip(volumes)
+    {
+        insert(
+            db,
+            wallets_to_tokens::Model {
+                wallet_id,
+                token_id,
+                volume,
+                created_at: now,
+                updated_at: now,
+                tenant_id,
+            }
+            .into_active_model(),
+        )
+        .await;
+    }
+    insert(
+        db,
+        edges_to_wallets::Model {
+            id,
+            edge_id: id,
+            src_wallet_id: id + 1,
+            dst_wallet_id: id + 2,
+            nft_wallet_id: id + 3,
+            closed_at: None,
+            created_at: now,
+            updated_at: now,
+            tenant_id,
+        }
+        .into_active_model(),
+    )
+    .await;
+    if funded {
+        insert(
+            db,
+            escrows::Model {
+                id,
+                edge_id: id,
+                escrow_wallet_id: id + 4,
+                token_id: id + 1,
+                amount: 5,
+                state: EscrowState::Open,
+                deadline: now + chrono::Duration::hours(1),
+                created_at: now,
+                updated_at: now,
+                tenant_id,
+            }
+            .into_active_model(),
+        )
+        .await;
+    }
+}
+
+async fn insert<A>(db: &DatabaseConnection, model: A)
+where
+    A: ActiveModelTrait + ActiveModelBehavior + Send,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+{
+    model.insert(db).await.unwrap();
+}
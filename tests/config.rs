@@ -0,0 +1,10 @@
+use bc_orm::config::Config;
+
+/// The documented example must start as is.
+#[test]
+fn the_example_config_is_valid() {
+    let config =
+        Config::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/bc_orm.example.toml")).unwrap();
+    config.validate().unwrap();
+    assert!(config.treasury.public_key.is_none());
+}
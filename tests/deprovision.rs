@@ -0,0 +1,253 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use async_trait::async_trait;
+use bc_orm::{
+    bigchain::NodeStats,
+    entity::{edges_to_wallets, prelude::*},
+    events::{Event, EventRecord},
+    ledger::{Block, InMemoryLedger, KeyPair, Ledger, LedgerError, OutputRef, Transaction},
+    repo::{ProvisionWallet, Repo, RepoError, TransferToken, DEFAULT_TENANT_ID},
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+/// An in-memory ledger refusing every transfer once `transfers_left` runs
+/// out.
+struct FlakyLedger {
+    inner: InMemoryLedger,
+    transfers_left: AtomicUsize,
+}
+
+#[async_trait]
+impl Ledger for FlakyLedger {
+    fn generate_keypair(&self) -> KeyPair {
+        self.inner.generate_keypair()
+    }
+
+    async fn create(
+        &self,
+        owner: &KeyPair,
+        amount: i32,
+        asset: Option<serde_json::Value>,
+        metadata: Option<serde_json::Value>,
+    ) -> anyhow::Result<String> {
+        self.inner.create(owner, amount, asset, metadata).await
+    }
+
+    async fn transfer(
+        &self,
+        owner: &KeyPair,
+        token: &str,
+        inputs: &[OutputRef],
+        outputs: &[(String, i32)],
+        metadata: serde_json::Value,
+    ) -> anyhow::Result<String> {
+        let left = self
+            .transfers_left
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                left.checked_sub(1)
+            });
+        if left.is_err() {
+            anyhow::bail!(LedgerError::Unavailable("node unreachable".to_string()));
+        }
+        self.inner
+            .transfer(owner, token, inputs, outputs, metadata)
+            .await
+    }
+
+    async fn list_outputs(
+        &self,
+        public_key: &str,
+        spent: Option<bool>,
+    ) -> anyhow::Result<Vec<OutputRef>> {
+        self.inner.list_outputs(public_key, spent).await
+    }
+
+    async fn get_transaction(&self, transaction_id: &str) -> anyhow::Result<Transaction> {
+        self.inner.get_transaction(transaction_id).await
+    }
+
+    async fn get_block(&self, height: i64) -> anyhow::Result<Option<Block>> {
+        self.inner.get_block(height).await
+    }
+
+    async fn stream_url(&self) -> anyhow::Result<String> {
+        self.inner.stream_url().await
+    }
+
+    fn stats(&self) -> Vec<NodeStats> {
+        self.inner.stats()
+    }
+}
+
+#[tokio::test]
+async fn deprovisioning_resumes_a_partial_sweep() {
+    let ledger = Arc::new(FlakyLedger {
+        inner: InMemoryLedger::new(),
+        transfers_left: AtomicUsize::new(usize::MAX),
+    });
+    let treasury = ledger.generate_keypair().public_key;
+    let repo = Arc::new(Repo {
+        db: common::database().await,
+        ledger: ledger.clone(),
+        ft_supply: 100,
+        mint_metadata: serde_json::Value::Null,
+        treasury_public_key: Some(treasury.clone()),
+        cache: None,
+        tenant_id: DEFAULT_TENANT_ID,
+    });
+    repo.clone()
+        .provision_wallet(ProvisionWallet {
+            edge_id: 1,
+            asset: serde_json::json!({}),
+        })
+        .await
+        .unwrap();
+    repo.clone()
+        .transfer_token(TransferToken { edge_id: 1 })
+        .await
+        .unwrap();
+
+    // the src_wallet is swept, then the node goes away
+    ledger.transfers_left.store(1, Ordering::SeqCst);
+    assert!(repo.clone().deprovision_edge(1).await.is_err());
+
+    let edge_wallet = repo.get_edge_wallet(1).await.unwrap();
+    assert!(edge_wallet.closed_at.is_some());
+    assert_eq!(edge_wallet.src_wallet.volume(&edge_wallet.token), 0);
+    assert_eq!(edge_wallet.dst_wallet.volume(&edge_wallet.token), 1);
+    let closed = repo
+        .clone()
+        .transfer_token(TransferToken { edge_id: 1 })
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        closed.downcast::<RepoError>().unwrap(),
+        RepoError::Conflict(_)
+    ));
+
+    ledger.transfers_left.store(usize::MAX, Ordering::SeqCst);
+    let edge_wallet = repo.clone().deprovision_edge(1).await.unwrap();
+    for wallet in [&edge_wallet.src_wallet, &edge_wallet.dst_wallet] {
+        assert!(wallet.balances.iter().all(|balance| balance.volume == 0));
+    }
+
+    // every FT unit and the NFT reached the treasury exactly once
+    let mut swept = Vec::new();
+    for output in ledger.list_outputs(&treasury, Some(false)).await.unwrap() {
+        let tx = ledger
+            .get_transaction(&output.transaction_id)
+            .await
+            .unwrap();
+        swept.push(
+            tx.outputs[output.output_index]
+                .amount
+                .parse::<i32>()
+                .unwrap(),
+        );
+    }
+    swept.sort();
+    assert_eq!(swept, [1, 1, 99]);
+    assert!(repo.reconcile_edges(&[1]).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn deprovisioning_sweeps_what_the_ledger_holds() {
+    let ledger = Arc::new(InMemoryLedger::new());
+    let treasury = ledger.generate_keypair().public_key;
+    let db = common::database().await;
+    let mut repo = Arc::into_inner(common::repo(db)).unwrap();
+    repo.ledger = ledger.clone();
+    repo.treasury_public_key = Some(treasury.clone());
+    let repo = Arc::new(repo);
+    repo.clone()
+        .provision_wallet(ProvisionWallet {
+            edge_id: 1,
+            asset: serde_json::json!({}),
+        })
+        .await
+        .unwrap();
+    let edge_wallet = repo.get_edge_wallet(1).await.unwrap();
+    let src_wallet = &edge_wallet.src_wallet;
+    let nft_wallet_id = EdgesToWallets::find()
+        .filter(edges_to_wallets::Column::EdgeId.eq(1))
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .nft_wallet_id;
+    let nft_wallet = Wallets::find_by_id(nft_wallet_id)
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // FT units reach the NFT wallet behind the balances' back
+    let inputs = ledger
+        .list_outputs(&src_wallet.public_key, Some(false))
+        .await
+        .unwrap();
+    ledger
+        .transfer(
+            &KeyPair {
+                public_key: src_wallet.public_key.clone(),
+                private_key: src_wallet.private_key.clone(),
+            },
+            &edge_wallet.token,
+            &inputs,
+            &[
+                (src_wallet.public_key.clone(), 70),
+                (nft_wallet.public_key.clone(), 30),
+            ],
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+
+    let edge_wallet = repo.clone().deprovision_edge(1).await.unwrap();
+    assert_eq!(edge_wallet.src_wallet.volume(&edge_wallet.token), 0);
+    for wallet in [&edge_wallet.src_wallet.public_key, &nft_wallet.public_key] {
+        assert!(ledger
+            .list_outputs(wallet, Some(false))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    let mut events = Vec::new();
+    for event in OutboxEvents::find().all(&repo.db).await.unwrap() {
+        events.push(EventRecord::try_from(event).unwrap().event);
+    }
+    let swept = |from: &str, amount| Event::TokensTransferred {
+        edge_id: Some(1),
+        token: edge_wallet.token.clone(),
+        from_public_key: from.to_string(),
+        to_public_key: treasury.clone(),
+        amount,
+    };
+    assert!(events.contains(&swept(&edge_wallet.src_wallet.public_key, 70)));
+    assert!(events.contains(&swept(&nft_wallet.public_key, 30)));
+    assert!(events.contains(&Event::NftTransferred {
+        edge_id: 1,
+        nft: edge_wallet.nft.clone(),
+        from_public_key: nft_wallet.public_key.clone(),
+        to_public_key: treasury.clone(),
+    }));
+}
+
+#[tokio::test]
+async fn deprovisioning_needs_a_treasury() {
+    let db = common::database().await;
+    common::seed(&db, DEFAULT_TENANT_ID, 10, false).await;
+    let mut repo = Arc::into_inner(common::repo(db)).unwrap();
+    repo.treasury_public_key = None;
+    let repo = Arc::new(repo);
+
+    let e = repo.clone().deprovision_edge(10).await.unwrap_err();
+    assert!(format!("{e:#}").contains("treasury.public_key"));
+    assert!(repo.get_edge_wallet(10).await.unwrap().closed_at.is_none());
+}
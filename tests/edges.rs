@@ -0,0 +1,42 @@
+mod common;
+
+use bc_orm::repo::RepoError;
+
+const TENANT: i32 = 1;
+
+#[tokio::test]
+async fn edges_resolve_alone_or_in_batches() {
+    let db = common::database().await;
+    common::seed(&db, TENANT, 10, true).await;
+    common::seed(&db, TENANT, 20, false).await;
+    common::seed(&db, 2, 30, true).await;
+    let repo = common::repo(db).for_tenant(TENANT);
+
+    let edge_wallet = repo.get_edge_wallet(10).await.unwrap();
+    assert_eq!(edge_wallet.token, "token-11");
+    assert_eq!(edge_wallet.nft, "token-12");
+    assert_eq!(edge_wallet.src_wallet.wallet_id, 11);
+    assert_eq!(edge_wallet.src_wallet.volume("token-11"), 95);
+    assert_eq!(edge_wallet.dst_wallet.wallet_id, 12);
+    assert!(edge_wallet.dst_wallet.balances.is_empty());
+
+    // unknown edges and other tenants' are left out
+    let edge_wallets = repo.get_edge_wallets(&[10, 20, 30, 99]).await.unwrap();
+    let mut edge_ids = edge_wallets.keys().copied().collect::<Vec<_>>();
+    edge_ids.sort();
+    assert_eq!(edge_ids, [10, 20]);
+    for (edge_id, edge_wallet) in edge_wallets {
+        let alone = repo.get_edge_wallet(edge_id).await.unwrap();
+        assert_eq!(
+            serde_json::to_value(&edge_wallet).unwrap(),
+            serde_json::to_value(&alone).unwrap()
+        );
+    }
+
+    let e = repo.get_edge_wallet(30).await.unwrap_err();
+    assert!(matches!(
+        e.downcast::<RepoError>().unwrap(),
+        RepoError::NotFound(_)
+    ));
+    assert!(repo.get_edge_wallets(&[]).await.unwrap().is_empty());
+}
@@ -0,0 +1,279 @@
+mod common;
+
+use std::sync::Arc;
+
+use bc_orm::{
+    entity::{prelude::*, sea_orm_active_enums::*, *},
+    ledger::KeyPair,
+    repo::{OpenEscrow, ProvisionWallet, Repo, RepoError},
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ConnectionTrait, EntityTrait, IntoActiveModel, QueryOrder,
+};
+
+/// Funded edge 10 with open escrow 10, see [`common::seed`].
+async fn setup() -> Arc<Repo> {
+    let db = common::database().await;
+    common::seed(&db, 0, 10, true).await;
+    common::repo(db)
+}
+
+async fn update_escrow(repo: &Repo, update: impl FnOnce(&mut escrows::ActiveModel)) {
+    let mut escrow = Escrows::find_by_id(10)
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_active_model();
+    update(&mut escrow);
+    escrow.update(&repo.db).await.unwrap();
+}
+
+fn repo_error(e: anyhow::Error) -> RepoError {
+    e.downcast::<RepoError>().unwrap()
+}
+
+#[tokio::test]
+async fn settling_refusals_are_typed() {
+    let repo = setup().await;
+
+    let unknown = repo.clone().refund_escrow(99).await.unwrap_err();
+    assert!(matches!(repo_error(unknown), RepoError::NotFound(_)));
+
+    update_escrow(&repo, |escrow| {
+        escrow.deadline = Set(chrono::Utc::now().fixed_offset() - chrono::Duration::minutes(1))
+    })
+    .await;
+    let late = repo.clone().release_escrow(10).await.unwrap_err();
+    assert!(matches!(repo_error(late), RepoError::Conflict(_)));
+
+    update_escrow(&repo, |escrow| escrow.state = Set(EscrowState::Released)).await;
+    let settled = repo.clone().refund_escrow(10).await.unwrap_err();
+    assert!(matches!(repo_error(settled), RepoError::Conflict(_)));
+}
+
+#[tokio::test]
+async fn ledger_refusals_roll_back_as_is() {
+    let repo = setup().await;
+
+    // the seeded escrow wallet holds nothing on the ledger
+    let unfunded = repo.clone().release_escrow(10).await.unwrap_err();
+    assert!(matches!(
+        repo_error(unfunded),
+        RepoError::InsufficientFunds(_)
+    ));
+    let escrow = Escrows::find_by_id(10)
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(escrow.state, EscrowState::Open);
+}
+
+#[tokio::test]
+async fn escrow_wallet_outlives_a_failed_open() {
+    let repo = common::repo(common::database().await);
+    let edge_wallet = repo
+        .clone()
+        .provision_wallet(ProvisionWallet {
+            edge_id: 1,
+            asset: serde_json::json!({}),
+        })
+        .await
+        .unwrap();
+
+    // recording the escrow fails once the units are on the ledger
+    repo.db
+        .execute_unprepared("DROP TABLE outbox_events")
+        .await
+        .unwrap();
+    let opened = repo
+        .clone()
+        .open_escrow(OpenEscrow {
+            edge_id: 1,
+            amount: 5,
+            deadline: chrono::Utc::now().fixed_offset() + chrono::Duration::hours(1),
+        })
+        .await;
+    assert!(opened.is_err());
+    assert!(Escrows::find().one(&repo.db).await.unwrap().is_none());
+
+    // the escrow wallet was kept, and its key still moves the units
+    let escrow_wallet = Wallets::find()
+        .order_by_desc(wallets::Column::Id)
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let inputs = repo
+        .ledger
+        .list_outputs(&escrow_wallet.public_key, Some(false))
+        .await
+        .unwrap();
+    assert_eq!(inputs.len(), 1);
+    repo.ledger
+        .transfer(
+            &KeyPair {
+                public_key: escrow_wallet.public_key,
+                private_key: escrow_wallet.private_key,
+            },
+            &edge_wallet.token,
+            &inputs,
+            &[(edge_wallet.src_wallet.public_key, 5)],
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn escrows_release_refund_and_expire() {
+    let repo = common::repo(common::database().await);
+    repo.clone()
+        .provision_wallet(ProvisionWallet {
+            edge_id: 1,
+            asset: serde_json::json!({}),
+        })
+        .await
+        .unwrap();
+    let open = |amount, deadline| {
+        repo.clone().open_escrow(OpenEscrow {
+            edge_id: 1,
+            amount,
+            deadline: chrono::Utc::now().fixed_offset() + deadline,
+        })
+    };
+    let volumes = || async {
+        let edge_wallet = repo.get_edge_wallet(1).await.unwrap();
+        (
+            edge_wallet.src_wallet.volume(&edge_wallet.token),
+            edge_wallet.dst_wallet.volume(&edge_wallet.token),
+        )
+    };
+
+    let released = open(5, chrono::Duration::hours(1)).await.unwrap();
+    let refunded = open(7, chrono::Duration::hours(1)).await.unwrap();
+    let expired = open(11, chrono::Duration::hours(1)).await.unwrap();
+    assert_eq!(released.state, EscrowState::Open);
+    assert_eq!(volumes().await, (77, 0));
+
+    let balances = repo
+        .get_wallet_balances(released.escrow_wallet_id)
+        .await
+        .unwrap();
+    assert_eq!(balances[0].volume, 5);
+    let released = repo.clone().release_escrow(released.id).await.unwrap();
+    assert_eq!(released.state, EscrowState::Released);
+    assert_eq!(volumes().await, (77, 5));
+
+    let refunded = repo.clone().refund_escrow(refunded.id).await.unwrap();
+    assert_eq!(refunded.state, EscrowState::Refunded);
+    assert_eq!(volumes().await, (84, 5));
+
+    // only the escrow past its deadline is swept
+    let mut escrow = expired.clone().into_active_model();
+    escrow.deadline = Set(chrono::Utc::now().fixed_offset() - chrono::Duration::minutes(1));
+    escrow.update(&repo.db).await.unwrap();
+    let fresh = open(3, chrono::Duration::hours(1)).await.unwrap();
+    let swept = repo.clone().refund_expired_escrows().await.unwrap();
+    assert_eq!(
+        swept.iter().map(|escrow| escrow.id).collect::<Vec<_>>(),
+        [expired.id]
+    );
+    assert_eq!(volumes().await, (92, 5));
+    let fresh = Escrows::find_by_id(fresh.id)
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(fresh.state, EscrowState::Open);
+
+    // the database agrees with the ledger on every wallet involved
+    assert!(repo.reconcile_edges(&[1]).await.unwrap().is_empty());
+    for escrow in [released, refunded] {
+        let wallet = Wallets::find_by_id(escrow.escrow_wallet_id)
+            .one(&repo.db)
+            .await
+            .unwrap()
+            .unwrap();
+        let outputs = repo
+            .ledger
+            .list_outputs(&wallet.public_key, Some(false))
+            .await
+            .unwrap();
+        assert!(outputs.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn settling_resumes_where_it_stopped() {
+    let repo = common::repo(common::database().await);
+    let edge_wallet = repo
+        .clone()
+        .provision_wallet(ProvisionWallet {
+            edge_id: 1,
+            asset: serde_json::json!({}),
+        })
+        .await
+        .unwrap();
+    repo.start_ledger_sync(1).await.unwrap();
+    let open = |amount| {
+        repo.clone().open_escrow(OpenEscrow {
+            edge_id: 1,
+            amount,
+            deadline: chrono::Utc::now().fixed_offset() + chrono::Duration::hours(1),
+        })
+    };
+    let set_state = |escrow: &escrows::Model, state| {
+        let mut escrow = escrow.clone().into_active_model();
+        escrow.state = Set(state);
+        escrow.update(&repo.db)
+    };
+    let released = open(5).await.unwrap();
+    let refunded = open(7).await.unwrap();
+
+    // the release was posted, but applying it failed
+    set_state(&released, EscrowState::Releasing).await.unwrap();
+    let escrow_wallet = Wallets::find_by_id(released.escrow_wallet_id)
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let inputs = repo
+        .ledger
+        .list_outputs(&escrow_wallet.public_key, Some(false))
+        .await
+        .unwrap();
+    repo.ledger
+        .transfer(
+            &KeyPair {
+                public_key: escrow_wallet.public_key,
+                private_key: escrow_wallet.private_key,
+            },
+            &edge_wallet.token,
+            &inputs,
+            &[(edge_wallet.dst_wallet.public_key.clone(), 5)],
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+    let e = repo.clone().refund_escrow(released.id).await.unwrap_err();
+    assert!(matches!(repo_error(e), RepoError::Conflict(_)));
+    let released = repo.clone().release_escrow(released.id).await.unwrap();
+    assert_eq!(released.state, EscrowState::Released);
+
+    // the refund stopped before the ledger, the sweeper picks it up
+    set_state(&refunded, EscrowState::Refunding).await.unwrap();
+    let swept = repo.clone().refund_expired_escrows().await.unwrap();
+    assert_eq!(
+        swept.iter().map(|escrow| escrow.id).collect::<Vec<_>>(),
+        [refunded.id]
+    );
+
+    // the transfer posted before is left to the ledger sync
+    repo.catch_up_ledger().await.unwrap();
+    let edge_wallet = repo.get_edge_wallet(1).await.unwrap();
+    assert_eq!(edge_wallet.src_wallet.volume(&edge_wallet.token), 95);
+    assert_eq!(edge_wallet.dst_wallet.volume(&edge_wallet.token), 5);
+    assert!(repo.reconcile_edges(&[1]).await.unwrap().is_empty());
+}
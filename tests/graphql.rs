@@ -0,0 +1,95 @@
+#![cfg(feature = "graphql")]
+
+mod common;
+
+use bc_orm::{
+    auth::AuthorizedRepo,
+    entity::*,
+    graphql::schema,
+    repo::{ApiClient, Scope},
+    ActiveModelTrait, IntoActiveModel,
+};
+
+const TENANT: i32 = 1;
+
+/// Wallet keys must never be reachable through the schema, whatever the
+/// caller's scopes.
+#[tokio::test]
+async fn wallets_expose_no_private_key() {
+    let schema = schema();
+    assert!(!schema.sdl().contains("privateKey"));
+
+    let response = schema
+        .execute("{ wallet(id: 1) { publicKey privateKey } }")
+        .await;
+    assert!(response.errors[0].message.contains("privateKey"));
+}
+
+/// A client restricted to edge 10 must not reach edge 20's wallets through
+/// a token both edges hold.
+#[tokio::test]
+async fn relations_stay_within_the_clients_edges() {
+    let db = common::database().await;
+    common::seed(&db, TENANT, 10, true).await;
+    common::seed(&db, TENANT, 20, true).await;
+    let now = chrono::Utc::now().fixed_offset();
+    wallets_to_tokens::Model {
+        wallet_id: 22,
+        token_id: 11,
+        volume: 3,
+        created_at: now,
+        updated_at: now,
+        tenant_id: TENANT,
+    }
+    .into_active_model()
+    .insert(&db)
+    .await
+    .unwrap();
+    let repo = common::repo(db);
+    let client = |edge_ids| ApiClient {
+        id: 1,
+        tenant_id: TENANT,
+        name: "client".to_string(),
+        scopes: vec![Scope::Read],
+        edge_ids,
+    };
+    let query = |wallet_id: i32| {
+        format!(
+            "{{ edge(edgeId: 10) {{ token {{ holders {{ volume wallet {{ id }} }} }} }} \
+             balance: wallet(id: {wallet_id}) {{ id }} }}"
+        )
+    };
+
+    let restricted = AuthorizedRepo::new(&repo, client(Some(vec![10])));
+    let response = schema()
+        .execute(async_graphql::Request::new(query(11)).data(restricted.clone()))
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    let holders = &response.data.into_json().unwrap()["edge"]["token"]["holders"];
+    assert_eq!(
+        holders,
+        &serde_json::json!([{ "volume": 95, "wallet": { "id": 11 } }])
+    );
+
+    let response = schema()
+        .execute(async_graphql::Request::new(query(22)).data(restricted))
+        .await;
+    assert_eq!(
+        response.errors[0].extensions.as_ref().unwrap().get("code"),
+        Some(&async_graphql::Value::from("FORBIDDEN"))
+    );
+
+    let unrestricted = AuthorizedRepo::new(&repo, client(None));
+    let response = schema()
+        .execute(async_graphql::Request::new(query(22)).data(unrestricted))
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    let holders = &response.data.into_json().unwrap()["edge"]["token"]["holders"];
+    let wallet_ids = holders
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|holder| holder["wallet"]["id"].as_i64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(wallet_ids, [11, 14, 22]);
+}
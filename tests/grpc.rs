@@ -0,0 +1,208 @@
+#![cfg(feature = "grpc")]
+
+mod common;
+
+use std::sync::Arc;
+
+use bc_orm::{
+    grpc::GrpcService,
+    repo::{NewApiClient, Repo, Scope, DEFAULT_TENANT_ID},
+};
+use bc_orm_grpc::v1::{
+    wallet_service_client::WalletServiceClient, GetEdgeRequest, ListEdgesRequest,
+    ProvisionWalletRequest, TransferTokenRequest,
+};
+use tonic::{
+    transport::{Channel, Server},
+    Code, Request,
+};
+
+/// A `GrpcService` over `repo` on a local port, and a client of it.
+async fn start(repo: Arc<Repo>) -> WalletServiceClient<Channel> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let incoming = futures::stream::unfold(listener, |listener| async move {
+        let stream = listener.accept().await.map(|(stream, _)| stream);
+        Some((stream, listener))
+    });
+    tokio::spawn(
+        Server::builder()
+            .add_service(GrpcService::new(repo).into_server())
+            .serve_with_incoming(incoming),
+    );
+    WalletServiceClient::connect(url).await.unwrap()
+}
+
+/// A client of `repo` with the given scopes and edges, and its API key.
+async fn api_key(repo: &Repo, name: &str, scopes: &[Scope], edge_ids: Option<Vec<i32>>) -> String {
+    repo.create_api_client(NewApiClient {
+        name: name.to_string(),
+        scopes: scopes.to_vec(),
+        edge_ids,
+    })
+    .await
+    .unwrap()
+    .key
+}
+
+fn with_key<T>(message: T, key: &str) -> Request<T> {
+    let mut request = Request::new(message);
+    let authorization = format!("Bearer {key}").parse().unwrap();
+    request
+        .metadata_mut()
+        .insert("authorization", authorization);
+    request
+}
+
+#[tokio::test]
+async fn calls_need_a_bearer_key() {
+    let db = common::database().await;
+    common::seed(&db, DEFAULT_TENANT_ID, 10, false).await;
+    let mut client = start(common::repo(db)).await;
+
+    let e = client
+        .get_edge(GetEdgeRequest { edge_id: 10 })
+        .await
+        .unwrap_err();
+    assert_eq!(e.code(), Code::Unauthenticated, "{e}");
+
+    let mut request = Request::new(GetEdgeRequest { edge_id: 10 });
+    let authorization = "Basic dXNlcjpwYXNz".parse().unwrap();
+    request
+        .metadata_mut()
+        .insert("authorization", authorization);
+    let e = client.get_edge(request).await.unwrap_err();
+    assert_eq!(e.code(), Code::Unauthenticated, "{e}");
+
+    // streams are refused before the first item
+    let request = with_key(ListEdgesRequest::default(), "bc_wrong");
+    let e = client.list_edges(request).await.unwrap_err();
+    assert_eq!(e.code(), Code::Unauthenticated, "{e}");
+}
+
+#[tokio::test]
+async fn repo_errors_map_to_statuses() {
+    let Some(db) = common::postgres().await else {
+        return;
+    };
+    common::seed(&db, DEFAULT_TENANT_ID, 10, true).await;
+    common::seed(&db, DEFAULT_TENANT_ID, 20, false).await;
+    let repo = common::repo(db);
+    let reader = api_key(&repo, "reader", &[Scope::Read], Some(vec![10])).await;
+    let transferer = api_key(&repo, "transferer", &[Scope::Transfer], None).await;
+    let mut client = start(repo).await;
+
+    let edge = client
+        .get_edge(with_key(GetEdgeRequest { edge_id: 10 }, &reader))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(edge.edge_id, 10);
+    assert_eq!(edge.src_wallet.unwrap().balances[0].volume, 95);
+
+    let codes = [
+        // another edge
+        (
+            GetEdgeRequest { edge_id: 20 },
+            &reader,
+            Code::PermissionDenied,
+        ),
+        // no such edge
+        (
+            GetEdgeRequest { edge_id: 30 },
+            &reader,
+            Code::PermissionDenied,
+        ),
+        // missing scope
+        (
+            GetEdgeRequest { edge_id: 10 },
+            &transferer,
+            Code::PermissionDenied,
+        ),
+    ];
+    for (message, key, code) in codes {
+        let e = client.get_edge(with_key(message, key)).await.unwrap_err();
+        assert_eq!(e.code(), code, "{e}");
+    }
+
+    let e = client
+        .transfer_token(with_key(TransferTokenRequest { edge_id: 30 }, &transferer))
+        .await
+        .unwrap_err();
+    assert_eq!(e.code(), Code::NotFound, "{e}");
+    // the seeded wallets hold nothing on the ledger
+    let e = client
+        .transfer_token(with_key(TransferTokenRequest { edge_id: 10 }, &transferer))
+        .await
+        .unwrap_err();
+    assert_eq!(e.code(), Code::FailedPrecondition, "{e}");
+}
+
+#[tokio::test]
+async fn provisioning_needs_an_asset_object() {
+    let Some(db) = common::postgres().await else {
+        return;
+    };
+    let repo = common::repo(db);
+    let key = api_key(&repo, "provisioner", &[Scope::Provision], None).await;
+    let mut client = start(repo).await;
+
+    for asset_json in ["", "{", "[1, 2]", "\"asset\""] {
+        let request = ProvisionWalletRequest {
+            edge_id: 1,
+            asset_json: asset_json.to_string(),
+        };
+        let e = client
+            .provision_wallet(with_key(request, &key))
+            .await
+            .unwrap_err();
+        assert_eq!(e.code(), Code::InvalidArgument, "{asset_json}: {e}");
+        assert!(e.message().contains("asset_json"), "{e}");
+    }
+
+    let request = ProvisionWalletRequest {
+        edge_id: 1,
+        asset_json: r#"{"name": "edge 1"}"#.to_string(),
+    };
+    let edge = client
+        .provision_wallet(with_key(request, &key))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(edge.edge_id, 1);
+}
+
+#[tokio::test]
+async fn list_edges_streams_every_page() {
+    let Some(db) = common::postgres().await else {
+        return;
+    };
+    // more than a page of 50
+    let edge_ids = (1..=60).map(|n| n * 10).collect::<Vec<_>>();
+    for &edge_id in &edge_ids {
+        common::seed(&db, DEFAULT_TENANT_ID, edge_id, false).await;
+    }
+    let repo = common::repo(db);
+    let key = api_key(&repo, "reader", &[Scope::Read], None).await;
+    let mut client = start(repo).await;
+
+    for descending in [false, true] {
+        let request = ListEdgesRequest {
+            descending,
+            ..Default::default()
+        };
+        let mut stream = client
+            .list_edges(with_key(request, &key))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut streamed = Vec::new();
+        while let Some(edge) = stream.message().await.unwrap() {
+            streamed.push(edge.edge_id);
+        }
+        if descending {
+            streamed.reverse();
+        }
+        assert_eq!(streamed, edge_ids);
+    }
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{header::AUTHORIZATION, Method, Request, StatusCode},
+    Router,
+};
+use bc_orm::{
+    bigchain::NodePool,
+    http::router,
+    repo::{NewApiClient, Repo, Scope, DEFAULT_TENANT_ID},
+};
+use tower::ServiceExt;
+
+/// The status and JSON body of `request` to `app`.
+async fn send(app: &Router, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn request(method: Method, uri: &str, key: Option<&str>) -> Request<Body> {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(key) = key {
+        request = request.header(AUTHORIZATION, format!("Bearer {key}"));
+    }
+    request.body(Body::empty()).unwrap()
+}
+
+/// A client of `repo` with every scope and the given edges, and its API key.
+async fn api_key(repo: &Repo, name: &str, edge_ids: Option<Vec<i32>>) -> String {
+    repo.create_api_client(NewApiClient {
+        name: name.to_string(),
+        scopes: vec![Scope::Provision, Scope::Transfer, Scope::Read],
+        edge_ids,
+    })
+    .await
+    .unwrap()
+    .key
+}
+
+#[tokio::test]
+async fn routes_need_a_bearer_key_but_health() {
+    let db = common::database().await;
+    common::seed(&db, DEFAULT_TENANT_ID, 10, false).await;
+    let app = router(common::repo(db));
+
+    let (status, body) = send(&app, request(Method::GET, "/health", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["nodes"].is_array());
+
+    for (method, uri) in [
+        (Method::GET, "/edges"),
+        (Method::POST, "/edges"),
+        (Method::GET, "/edges/10"),
+        (Method::DELETE, "/edges/10"),
+        (Method::POST, "/edges/10/transfers"),
+        (Method::GET, "/wallets"),
+        (Method::GET, "/wallets/11/balances"),
+        (Method::GET, "/tokens"),
+        (Method::POST, "/transfers"),
+        (Method::POST, "/escrows"),
+        (Method::POST, "/escrows/10/release"),
+        (Method::POST, "/escrows/10/refund"),
+    ] {
+        for key in [None, Some("bc_wrong")] {
+            let (status, body) = send(&app, request(method.clone(), uri, key)).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{method} {uri}");
+            assert!(body["error"].is_string(), "{method} {uri}: {body}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn errors_map_to_statuses() {
+    let Some(db) = common::postgres().await else {
+        return;
+    };
+    common::seed(&db, DEFAULT_TENANT_ID, 10, true).await;
+    common::seed(&db, DEFAULT_TENANT_ID, 20, false).await;
+    let repo = common::repo(db);
+    let key = api_key(&repo, "unrestricted", None).await;
+    let restricted = api_key(&repo, "restricted", Some(vec![10])).await;
+    let app = router(repo.clone());
+
+    let (status, body) = send(&app, request(Method::GET, "/edges/10", Some(&key))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["edge_id"], 10);
+
+    for (method, uri, key, expected) in [
+        (Method::GET, "/edges/30", &key, StatusCode::NOT_FOUND),
+        (Method::GET, "/edges/20", &restricted, StatusCode::FORBIDDEN),
+        (Method::GET, "/edges?limit=0", &key, StatusCode::BAD_REQUEST),
+        (Method::GET, "/edges/ten", &key, StatusCode::BAD_REQUEST),
+        // edge 10 has an open escrow
+        (Method::DELETE, "/edges/10", &key, StatusCode::CONFLICT),
+        // and nothing on the ledger
+        (
+            Method::POST,
+            "/edges/10/transfers",
+            &key,
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+    ] {
+        let (status, body) = send(&app, request(method.clone(), uri, Some(key))).await;
+        assert_eq!(status, expected, "{method} {uri}: {body}");
+        assert!(body["error"].is_string(), "{method} {uri}: {body}");
+    }
+
+    let provision = Request::builder()
+        .method(Method::POST)
+        .uri("/edges")
+        .header(AUTHORIZATION, format!("Bearer {key}"))
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"edge_id": 1, "asset": [1, 2]}"#))
+        .unwrap();
+    let (status, body) = send(&app, provision).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "asset must be a JSON object");
+
+    // ledger failures are reported without their details
+    drop(app);
+    let mut repo = Arc::into_inner(repo).unwrap();
+    repo.ledger = Arc::new(NodePool::new(["http://127.0.0.1:1/api/v1"]));
+    let app = router(Arc::new(repo));
+    let (status, body) = send(
+        &app,
+        request(Method::POST, "/edges/10/transfers", Some(&key)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["error"], "Bad Gateway");
+}
@@ -0,0 +1,294 @@
+mod common;
+
+use std::sync::Arc;
+
+use bc_orm::{
+    entity::prelude::*,
+    ledger::{InMemoryLedger, KeyPair, Ledger, LedgerError, OutputRef},
+    repo::{ProvisionWallet, Repo, TransferToken},
+    ActiveModelTrait,
+    ActiveValue::Set,
+    EntityTrait, IntoActiveModel,
+};
+use serde_json::json;
+
+const EDGE: i32 = 1;
+
+/// A ledger where `owner` holds the 10 units of a freshly minted token.
+async fn minted() -> (InMemoryLedger, KeyPair, String) {
+    let ledger = InMemoryLedger::new();
+    let owner = ledger.generate_keypair();
+    let token = ledger
+        .create(&owner, 10, Some(json!({ "name": "ft" })), None)
+        .await
+        .unwrap();
+    (ledger, owner, token)
+}
+
+fn output(transaction_id: &str, output_index: usize) -> OutputRef {
+    OutputRef {
+        transaction_id: transaction_id.to_string(),
+        output_index,
+    }
+}
+
+fn rejected(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref(), Some(LedgerError::Rejected(_)))
+}
+
+async fn provisioned() -> Arc<Repo> {
+    let repo = common::repo(common::database().await);
+    repo.clone()
+        .provision_wallet(ProvisionWallet {
+            edge_id: EDGE,
+            asset: json!({ "edge": EDGE }),
+        })
+        .await
+        .unwrap();
+    repo
+}
+
+#[tokio::test]
+async fn transfers_spend_outputs_once() {
+    let (ledger, owner, token) = minted().await;
+    let receiver = ledger.generate_keypair();
+    assert_eq!(
+        ledger.list_outputs(&owner.public_key, None).await.unwrap(),
+        [output(&token, 0)]
+    );
+
+    let transfer = ledger
+        .transfer(
+            &owner,
+            &token,
+            &[output(&token, 0)],
+            &[
+                (receiver.public_key.clone(), 3),
+                (owner.public_key.clone(), 7),
+            ],
+            json!({}),
+        )
+        .await
+        .unwrap();
+
+    let tx = ledger.get_transaction(&transfer).await.unwrap();
+    assert_eq!(tx.operation, "TRANSFER");
+    assert_eq!(tx.token(), Some(token.as_str()));
+    assert_eq!(tx.outputs[0].amount, "3");
+    assert_eq!(
+        ledger
+            .list_outputs(&owner.public_key, Some(false))
+            .await
+            .unwrap(),
+        [output(&transfer, 1)]
+    );
+    assert_eq!(
+        ledger
+            .list_outputs(&owner.public_key, Some(true))
+            .await
+            .unwrap(),
+        [output(&token, 0)]
+    );
+
+    // double spend
+    let again = ledger
+        .transfer(
+            &owner,
+            &token,
+            &[output(&token, 0)],
+            &[(receiver.public_key.clone(), 10)],
+            json!({}),
+        )
+        .await;
+    assert!(again.is_err());
+
+    let block = ledger.get_block(2).await.unwrap().unwrap();
+    assert_eq!(block.transactions[0].id, transfer);
+    assert!(ledger.get_block(3).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn rejects_transfers_a_node_would() {
+    let (ledger, owner, token) = minted().await;
+    let thief = ledger.generate_keypair();
+    let other_token = ledger.create(&thief, 5, None, None).await.unwrap();
+    let pay = |amount| vec![(thief.public_key.clone(), amount)];
+
+    // signed by someone else than the owner
+    let stolen = ledger
+        .transfer(&thief, &token, &[output(&token, 0)], &pay(10), json!({}))
+        .await;
+    let stolen = stolen.unwrap_err();
+    assert!(stolen.to_string().contains("signature"));
+    assert!(rejected(&stolen));
+
+    // paying out less or more than the inputs hold
+    for amount in [9, 11] {
+        let unbalanced = ledger
+            .transfer(
+                &owner,
+                &token,
+                &[output(&token, 0)],
+                &pay(amount),
+                json!({}),
+            )
+            .await;
+        assert!(rejected(&unbalanced.unwrap_err()));
+    }
+
+    // spending an output of another token
+    let mixed = ledger
+        .transfer(
+            &thief,
+            &token,
+            &[output(&other_token, 0)],
+            &pay(5),
+            json!({}),
+        )
+        .await;
+    assert!(rejected(&mixed.unwrap_err()));
+
+    // paying a key that is not one
+    let invalid = ledger
+        .transfer(
+            &owner,
+            &token,
+            &[output(&token, 0)],
+            &[("treasury".to_string(), 10)],
+            json!({}),
+        )
+        .await;
+    assert!(rejected(&invalid.unwrap_err()));
+
+    // nothing above was committed
+    assert!(ledger.get_block(3).await.unwrap().is_none());
+    assert_eq!(
+        ledger
+            .list_outputs(&owner.public_key, Some(false))
+            .await
+            .unwrap(),
+        [output(&token, 0)]
+    );
+}
+
+#[tokio::test]
+async fn provisioned_edges_match_the_ledger() {
+    let repo = provisioned().await;
+    let edge_wallet = repo
+        .clone()
+        .transfer_token(TransferToken { edge_id: EDGE })
+        .await
+        .unwrap();
+
+    assert_eq!(edge_wallet.src_wallet.volume(&edge_wallet.token), 99);
+    assert_eq!(edge_wallet.dst_wallet.volume(&edge_wallet.token), 1);
+    assert!(repo.reconcile_edges(&[EDGE]).await.unwrap().is_empty());
+
+    let edge_wallet = repo.clone().deprovision_edge(EDGE).await.unwrap();
+    assert!(edge_wallet.closed_at.is_some());
+    let swept = repo
+        .ledger
+        .list_outputs(repo.treasury_public_key.as_ref().unwrap(), Some(false))
+        .await
+        .unwrap();
+    assert!(!swept.is_empty());
+}
+
+#[tokio::test]
+async fn syncs_transfers_made_elsewhere_once() {
+    let repo = provisioned().await;
+    repo.clone()
+        .transfer_token(TransferToken { edge_id: EDGE })
+        .await
+        .unwrap();
+    repo.start_ledger_sync(1).await.unwrap();
+    // blocks of the provisioning and transfer, all recorded already
+    assert_eq!(repo.catch_up_ledger().await.unwrap(), 3);
+
+    let edge_wallet = repo.get_edge_wallet(EDGE).await.unwrap();
+    assert_eq!(edge_wallet.src_wallet.volume(&edge_wallet.token), 99);
+    assert_eq!(edge_wallet.dst_wallet.volume(&edge_wallet.token), 1);
+
+    // the dst wallet pays the unit back behind the repo's back
+    let dst = KeyPair {
+        public_key: edge_wallet.dst_wallet.public_key.clone(),
+        private_key: edge_wallet.dst_wallet.private_key.clone(),
+    };
+    let inputs = repo
+        .ledger
+        .list_outputs(&dst.public_key, Some(false))
+        .await
+        .unwrap();
+    repo.ledger
+        .transfer(
+            &dst,
+            &edge_wallet.token,
+            &inputs,
+            &[(edge_wallet.src_wallet.public_key.clone(), 1)],
+            json!({}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(repo.catch_up_ledger().await.unwrap(), 1);
+
+    let edge_wallet = repo.get_edge_wallet(EDGE).await.unwrap();
+    assert_eq!(edge_wallet.src_wallet.volume(&edge_wallet.token), 100);
+    assert_eq!(edge_wallet.dst_wallet.volume(&edge_wallet.token), 0);
+    assert!(repo.reconcile_edges(&[EDGE]).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn history_records_only_the_units_synced() {
+    let repo = provisioned().await;
+    let edge_wallet = repo
+        .clone()
+        .transfer_token(TransferToken { edge_id: EDGE })
+        .await
+        .unwrap();
+    repo.start_ledger_sync(1).await.unwrap();
+    repo.catch_up_ledger().await.unwrap();
+
+    // the dst wallet's unit went missing from the database
+    let dst_wallet = &edge_wallet.dst_wallet;
+    let balance = WalletsToTokens::find_by_id((dst_wallet.wallet_id, edge_wallet.token_id))
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut balance = balance.into_active_model();
+    balance.volume = Set(0);
+    balance.update(&repo.db).await.unwrap();
+
+    let dst = KeyPair {
+        public_key: dst_wallet.public_key.clone(),
+        private_key: dst_wallet.private_key.clone(),
+    };
+    let inputs = repo
+        .ledger
+        .list_outputs(&dst.public_key, Some(false))
+        .await
+        .unwrap();
+    repo.ledger
+        .transfer(
+            &dst,
+            &edge_wallet.token,
+            &inputs,
+            &[(edge_wallet.src_wallet.public_key.clone(), 1)],
+            json!({}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(repo.catch_up_ledger().await.unwrap(), 1);
+
+    let edge_wallet = repo.get_edge_wallet(EDGE).await.unwrap();
+    assert_eq!(edge_wallet.dst_wallet.volume(&edge_wallet.token), 0);
+    // the unit it was paid, but not the one it spent without holding it
+    let history = repo.wallet_history(dst_wallet.wallet_id).await.unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|change| change.amount)
+            .collect::<Vec<_>>(),
+        [1]
+    );
+}
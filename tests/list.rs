@@ -0,0 +1,189 @@
+mod common;
+
+use std::sync::Arc;
+
+use bc_orm::repo::{
+    ListEdges, ListTokens, ListWallets, Repo, RepoError, SortBy, SortOrder, DEFAULT_TENANT_ID,
+};
+
+/// Funded edges 30, 10 and 20 seeded in that order, see [`common::seed`]:
+/// the rows of each share a `created_at`, later than the previous edge's.
+async fn setup() -> Arc<Repo> {
+    let db = common::database().await;
+    for id in [30, 10, 20] {
+        common::seed(&db, DEFAULT_TENANT_ID, id, true).await;
+    }
+    common::repo(db)
+}
+
+fn is_invalid(e: &anyhow::Error) -> bool {
+    e.chain()
+        .any(|cause| matches!(cause.downcast_ref(), Some(RepoError::Invalid(_))))
+}
+
+/// Every wallet id of the listing, fetched `limit` at a time.
+async fn wallet_ids(repo: &Repo, query: impl Fn(Option<String>) -> ListWallets) -> Vec<i32> {
+    let mut ids = Vec::new();
+    let mut after = None;
+    loop {
+        let page = repo.list_wallets(query(after)).await.unwrap();
+        ids.extend(page.items.iter().map(|wallet| wallet.wallet_id));
+        match page.next_cursor {
+            Some(cursor) => after = Some(cursor),
+            None => return ids,
+        }
+    }
+}
+
+#[tokio::test]
+async fn page_sizes_are_bounded() {
+    let repo = setup().await;
+    for limit in [0, 501] {
+        let query = ListEdges {
+            limit: Some(limit),
+            ..Default::default()
+        };
+        let e = repo.list_edges(query).await.unwrap_err();
+        assert!(is_invalid(&e), "{limit}: {e:#}");
+        let query = ListWallets {
+            limit: Some(limit),
+            ..Default::default()
+        };
+        let e = repo.list_wallets(query).await.unwrap_err();
+        assert!(is_invalid(&e), "{limit}: {e:#}");
+        let query = ListTokens {
+            limit: Some(limit),
+            ..Default::default()
+        };
+        let e = repo.list_tokens(query).await.unwrap_err();
+        assert!(is_invalid(&e), "{limit}: {e:#}");
+    }
+
+    let page = repo
+        .list_edges(ListEdges {
+            limit: Some(2),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        page.items
+            .iter()
+            .map(|edge| edge.edge_id)
+            .collect::<Vec<_>>(),
+        [10, 20]
+    );
+    let last = repo
+        .list_edges(ListEdges {
+            limit: Some(500),
+            after: page.next_cursor,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        last.items
+            .iter()
+            .map(|edge| edge.edge_id)
+            .collect::<Vec<_>>(),
+        [30]
+    );
+    assert!(last.next_cursor.is_none());
+
+    let e = repo
+        .list_edges(ListEdges {
+            after: Some("not a cursor".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert!(is_invalid(&e), "{e:#}");
+}
+
+#[tokio::test]
+async fn listings_sort_by_creation_then_id() {
+    let repo = setup().await;
+    let by_created_at = |order| {
+        move |after| ListWallets {
+            sort: SortBy::CreatedAt,
+            order,
+            after,
+            limit: Some(3),
+            ..Default::default()
+        }
+    };
+    assert_eq!(
+        wallet_ids(&repo, by_created_at(SortOrder::Asc)).await,
+        [31, 32, 33, 34, 11, 12, 13, 14, 21, 22, 23, 24]
+    );
+    assert_eq!(
+        wallet_ids(&repo, by_created_at(SortOrder::Desc)).await,
+        [24, 23, 22, 21, 14, 13, 12, 11, 34, 33, 32, 31]
+    );
+
+    let page = repo
+        .list_tokens(ListTokens {
+            sort: SortBy::CreatedAt,
+            limit: Some(3),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        page.items.iter().map(|token| token.id).collect::<Vec<_>>(),
+        [31, 32, 11]
+    );
+    let page = repo
+        .list_edges(ListEdges {
+            sort: SortBy::CreatedAt,
+            order: SortOrder::Desc,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        page.items
+            .iter()
+            .map(|edge| edge.edge_id)
+            .collect::<Vec<_>>(),
+        [20, 10, 30]
+    );
+}
+
+#[tokio::test]
+async fn wallets_sort_by_the_volume_of_a_token() {
+    let repo = setup().await;
+    // edge 10's FT: 95 units in its src wallet, 5 in its escrow wallet
+    let by_volume = |order| {
+        move |after| ListWallets {
+            token: Some("token-11".to_string()),
+            sort: SortBy::Volume,
+            order,
+            after,
+            limit: Some(1),
+            ..Default::default()
+        }
+    };
+    assert_eq!(wallet_ids(&repo, by_volume(SortOrder::Asc)).await, [14, 11]);
+    assert_eq!(
+        wallet_ids(&repo, by_volume(SortOrder::Desc)).await,
+        [11, 14]
+    );
+
+    let e = repo
+        .list_wallets(ListWallets {
+            sort: SortBy::Volume,
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert!(is_invalid(&e), "{e:#}");
+    let e = repo
+        .list_tokens(ListTokens {
+            sort: SortBy::Volume,
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert!(is_invalid(&e), "{e:#}");
+}
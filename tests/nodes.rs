@@ -0,0 +1,128 @@
+use std::{
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{extract::State, http::StatusCode, Json, Router};
+use bc_orm::{
+    bigchain::{NodePool, NodeSelection},
+    ledger::{Ledger, LedgerError},
+};
+
+/// Nothing listens on port 1, so connecting fails right away.
+const UNREACHABLE: &str = "http://127.0.0.1:1/api/v1";
+
+/// A stand-in BigchainDB node answering every request with `status` and a
+/// body that parses as the API root and as an empty block, naming the node.
+#[derive(Clone)]
+struct Node {
+    url: String,
+    status: Arc<AtomicU16>,
+}
+
+impl Node {
+    async fn start(name: &'static str) -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let node = Node {
+            url: format!("http://{}/api/v1", listener.local_addr().unwrap()),
+            status: Arc::new(AtomicU16::new(200)),
+        };
+        let app = Router::new()
+            .fallback(move |State(node): State<Node>| async move {
+                let status = StatusCode::from_u16(node.status.load(Ordering::SeqCst)).unwrap();
+                let body = serde_json::json!({
+                    "streams": format!("ws://{name}"),
+                    "height": 1,
+                    "transactions": [],
+                });
+                (status, Json(body))
+            })
+            .with_state(node.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        node
+    }
+
+    fn respond_with(&self, status: StatusCode) {
+        self.status.store(status.as_u16(), Ordering::SeqCst);
+    }
+}
+
+/// `(healthy, requests, failures)` of every node.
+fn stats(pool: &NodePool) -> Vec<(bool, u64, u64)> {
+    pool.stats()
+        .into_iter()
+        .map(|node| (node.healthy, node.requests, node.failures))
+        .collect()
+}
+
+#[tokio::test]
+async fn reads_fail_over_in_order_and_put_failing_nodes_aside() {
+    let failing = Node::start("failing").await;
+    failing.respond_with(StatusCode::INTERNAL_SERVER_ERROR);
+    let healthy = Node::start("healthy").await;
+    let cooldown = Duration::from_millis(300);
+    let pool = NodePool::new([UNREACHABLE.to_string(), failing.url.clone(), healthy.url])
+        .selection(NodeSelection::Priority)
+        .cooldown(cooldown);
+
+    assert!(pool.get_block(1).await.unwrap().is_some());
+    assert_eq!(stats(&pool), [(false, 1, 1), (false, 1, 1), (true, 1, 0)]);
+
+    // nodes put aside are skipped during their cooldown
+    assert!(pool.get_block(1).await.unwrap().is_some());
+    assert_eq!(stats(&pool), [(false, 1, 1), (false, 1, 1), (true, 2, 0)]);
+
+    // and tried in order again after it
+    failing.respond_with(StatusCode::OK);
+    tokio::time::sleep(cooldown).await;
+    assert!(pool.get_block(1).await.unwrap().is_some());
+    assert_eq!(stats(&pool), [(false, 2, 2), (true, 2, 1), (true, 2, 0)]);
+}
+
+#[tokio::test]
+async fn reads_spread_over_the_nodes() {
+    let a = Node::start("a").await;
+    let b = Node::start("b").await;
+    let pool = NodePool::new([a.url, b.url]).selection(NodeSelection::RoundRobin);
+
+    for _ in 0..4 {
+        pool.get_block(1).await.unwrap();
+    }
+    assert_eq!(stats(&pool), [(true, 2, 0), (true, 2, 0)]);
+}
+
+#[tokio::test]
+async fn the_stream_is_looked_up_like_any_read() {
+    let first = Node::start("first").await;
+    let second = Node::start("second").await;
+    first.respond_with(StatusCode::INTERNAL_SERVER_ERROR);
+    let pool = NodePool::new([UNREACHABLE.to_string(), first.url, second.url])
+        .selection(NodeSelection::Priority);
+
+    // past a node failing after receiving the request too
+    assert_eq!(pool.stream_url().await.unwrap(), "ws://second");
+    assert_eq!(stats(&pool), [(false, 1, 1), (false, 1, 1), (true, 1, 0)]);
+}
+
+#[tokio::test]
+async fn failures_are_ledger_errors() {
+    let node = Node::start("node").await;
+    let pool = NodePool::new([node.url.clone()]);
+    let ledger_error = |e: anyhow::Error| e.downcast::<LedgerError>().unwrap();
+
+    node.respond_with(StatusCode::BAD_REQUEST);
+    let e = pool.list_outputs("key", None).await.unwrap_err();
+    assert!(matches!(ledger_error(e), LedgerError::Rejected(_)));
+
+    node.respond_with(StatusCode::NOT_FOUND);
+    assert!(pool.get_block(2).await.unwrap().is_none());
+    let e = pool.get_transaction("id").await.unwrap_err();
+    assert!(matches!(ledger_error(e), LedgerError::NotFound(_)));
+
+    let pool = NodePool::new([UNREACHABLE]);
+    let e = pool.get_block(1).await.unwrap_err();
+    assert!(matches!(ledger_error(e), LedgerError::Unavailable(_)));
+}
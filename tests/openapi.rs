@@ -0,0 +1,24 @@
+use bc_orm::http::ApiDoc;
+use utoipa::OpenApi;
+
+const SPEC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+
+/// `openapi.json` is the published contract of the HTTP API. Regenerate it
+/// after changing a request or response type with
+/// `UPDATE_OPENAPI=1 cargo test --test openapi`.
+#[test]
+fn openapi_json_matches_api_types() {
+    let generated = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        std::fs::write(SPEC, &generated).unwrap();
+        return;
+    }
+
+    let committed = std::fs::read_to_string(SPEC).unwrap_or_default();
+    assert!(
+        committed == generated,
+        "openapi.json is out of date with the API types, \
+         run `UPDATE_OPENAPI=1 cargo test --test openapi` and commit the result"
+    );
+}
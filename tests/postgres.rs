@@ -0,0 +1,207 @@
+//! What an in-memory SQLite database cannot stand in for: array columns and
+//! relays sharing the outbox and the webhook deliveries with `SKIP LOCKED`.
+//! Skipped unless `TEST_DATABASE_URL` is set, see [`common::postgres`].
+mod common;
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use axum::{extract::State, http::HeaderMap, routing::post, Router};
+use bc_orm::{
+    auth::AuthorizedRepo,
+    entity::{outbox_events, prelude::*, sea_orm_active_enums::*},
+    events::{EventRecord, EventSink, EventType},
+    repo::{
+        NewApiClient, NewWebhook, Repo, RepoError, RetryPolicy, Scope, WebhookSink,
+        DEFAULT_TENANT_ID, DELIVERY_HEADER,
+    },
+    DatabaseConnection, EntityTrait, QueryOrder,
+};
+use reqwest::Client;
+
+const RELAYS: usize = 4;
+const EDGES: i32 = 8;
+
+/// `EDGES` empty edges of the default tenant, deprovisioned so that the
+/// outbox holds an event for each of them.
+async fn deprovisioned(db: DatabaseConnection) -> Arc<Repo> {
+    for edge_id in 1..=EDGES {
+        common::seed(&db, DEFAULT_TENANT_ID, edge_id * 10, false).await;
+    }
+    let repo = common::repo(db);
+    for edge_id in 1..=EDGES {
+        repo.clone().deprovision_edge(edge_id * 10).await.unwrap();
+    }
+    repo
+}
+
+#[tokio::test]
+async fn api_clients_keep_their_edges() {
+    let Some(db) = common::postgres().await else {
+        return;
+    };
+    let repo = common::repo(db);
+    common::seed(&repo.db, DEFAULT_TENANT_ID, 10, false).await;
+    common::seed(&repo.db, DEFAULT_TENANT_ID, 20, false).await;
+
+    let restricted = repo
+        .create_api_client(NewApiClient {
+            name: "restricted".to_string(),
+            scopes: vec![Scope::Read],
+            edge_ids: Some(vec![10]),
+        })
+        .await
+        .unwrap();
+    let unrestricted = repo
+        .create_api_client(NewApiClient {
+            name: "unrestricted".to_string(),
+            scopes: vec![Scope::Read, Scope::Transfer],
+            edge_ids: None,
+        })
+        .await
+        .unwrap();
+
+    let client = repo.authenticate(&restricted.key).await.unwrap();
+    assert_eq!(client.edge_ids, Some(vec![10]));
+    assert_eq!(client.scopes, [Scope::Read]);
+    let client = repo.authenticate(&unrestricted.key).await.unwrap();
+    assert_eq!(client.edge_ids, None);
+
+    let authorized =
+        AuthorizedRepo::authenticate(repo.clone(), Some(&format!("Bearer {}", restricted.key)))
+            .await
+            .unwrap();
+    assert!(authorized.client().require_edge(Scope::Read, 10).is_ok());
+    let e = authorized
+        .client()
+        .require_edge(Scope::Read, 20)
+        .unwrap_err();
+    assert!(matches!(
+        e.downcast::<RepoError>().unwrap(),
+        RepoError::Forbidden(_)
+    ));
+    assert_eq!(
+        authorized.wallet_ids(Scope::Read).await.unwrap(),
+        Some(vec![11, 12, 13])
+    );
+
+    let e = repo.authenticate("bc_wrong").await.unwrap_err();
+    assert!(matches!(
+        e.downcast::<RepoError>().unwrap(),
+        RepoError::Unauthenticated(_)
+    ));
+}
+
+/// Takes its time over every event, so that relays publish side by side.
+#[derive(Default)]
+struct SlowSink {
+    published: Mutex<Vec<i32>>,
+}
+
+#[async_trait]
+impl EventSink for SlowSink {
+    async fn publish(&self, event: &EventRecord) -> anyhow::Result<()> {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        self.published.lock().unwrap().push(event.id);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn relays_share_the_outbox() {
+    let Some(db) = common::postgres().await else {
+        return;
+    };
+    let repo = deprovisioned(db).await;
+    let sink = Arc::new(SlowSink::default());
+
+    let relays = (0..RELAYS).map(|_| {
+        let (repo, sink) = (repo.clone(), sink.clone());
+        tokio::spawn(async move {
+            let mut published = 0;
+            loop {
+                match repo.publish_events(sink.as_ref(), 1).await.unwrap() {
+                    0 => return published,
+                    n => published += n,
+                }
+            }
+        })
+    });
+    let published = futures::future::try_join_all(relays).await.unwrap();
+
+    // every relay took a share, and no event was published twice
+    assert!(published.iter().all(|&n| n > 0), "{published:?}");
+    let mut ids = sink.published.lock().unwrap().clone();
+    ids.sort();
+    let events = OutboxEvents::find()
+        .order_by_asc(outbox_events::Column::Id)
+        .all(&repo.db)
+        .await
+        .unwrap();
+    assert_eq!(ids, events.iter().map(|event| event.id).collect::<Vec<_>>());
+    assert!(events.iter().all(|event| event.published_at.is_some()));
+}
+
+/// Counts the requests of every delivery, answering each after a while.
+async fn slow_receiver() -> (String, Arc<Mutex<BTreeMap<i32, usize>>>) {
+    async fn record(State(received): State<Arc<Mutex<BTreeMap<i32, usize>>>>, headers: HeaderMap) {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let delivery_id = headers[DELIVERY_HEADER].to_str().unwrap().parse().unwrap();
+        *received.lock().unwrap().entry(delivery_id).or_default() += 1;
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let received = Arc::new(Mutex::new(BTreeMap::new()));
+    let app = Router::new()
+        .route("/hook", post(record))
+        .with_state(received.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (url, received)
+}
+
+#[tokio::test]
+async fn relays_share_the_webhook_deliveries() {
+    let Some(db) = common::postgres().await else {
+        return;
+    };
+    let repo = deprovisioned(db).await;
+    let (url, received) = slow_receiver().await;
+    repo.create_webhook(NewWebhook {
+        url,
+        event_types: vec![EventType::EdgeDeprovisioned],
+    })
+    .await
+    .unwrap();
+    let sink = WebhookSink::new(repo.clone());
+    while repo.publish_events(&sink, 100).await.unwrap() > 0 {}
+
+    let relays = (0..RELAYS).map(|_| {
+        let repo = repo.clone();
+        tokio::spawn(async move {
+            let (client, policy) = (Client::new(), RetryPolicy::default());
+            let mut attempted = 0;
+            loop {
+                match repo.deliver_webhooks(&client, &policy, 1).await.unwrap() {
+                    0 => return attempted,
+                    n => attempted += n,
+                }
+            }
+        })
+    });
+    let attempted = futures::future::try_join_all(relays).await.unwrap();
+
+    assert!(attempted.iter().all(|&n| n > 0), "{attempted:?}");
+    let deliveries = WebhookDeliveries::find().all(&repo.db).await.unwrap();
+    assert_eq!(deliveries.len(), EDGES as usize);
+    assert!(deliveries
+        .iter()
+        .all(|delivery| delivery.state == WebhookDeliveryState::Delivered));
+    let received = received.lock().unwrap().clone();
+    assert_eq!(received.len(), EDGES as usize);
+    assert!(received.values().all(|&requests| requests == 1));
+}
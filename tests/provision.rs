@@ -0,0 +1,33 @@
+mod common;
+
+use bc_orm::{
+    entity::prelude::*,
+    repo::{ProvisionWallet, RepoError},
+    EntityTrait,
+};
+
+fn edge(edge_id: i32) -> ProvisionWallet {
+    ProvisionWallet {
+        edge_id,
+        asset: serde_json::json!({ "edge": edge_id }),
+    }
+}
+
+#[tokio::test]
+async fn provisioning_an_edge_twice_conflicts() {
+    let repo = common::repo(common::database().await);
+    repo.clone().provision_wallet(edge(1)).await.unwrap();
+    let requests = repo.ledger.stats()[0].requests;
+
+    let again = repo.clone().provision_wallet(edge(1)).await.unwrap_err();
+    assert!(matches!(
+        again.downcast::<RepoError>().unwrap(),
+        RepoError::Conflict(_)
+    ));
+    // refused before minting anything
+    assert_eq!(repo.ledger.stats()[0].requests, requests);
+
+    // edge ids are per tenant
+    repo.for_tenant(2).provision_wallet(edge(1)).await.unwrap();
+    assert_eq!(EdgesToWallets::find().all(&repo.db).await.unwrap().len(), 2);
+}
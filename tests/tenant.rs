@@ -0,0 +1,154 @@
+mod common;
+
+use std::sync::Arc;
+
+use bc_orm::{
+    auth::AuthorizedRepo,
+    entity::{prelude::*, sea_orm_active_enums::*},
+    repo::{ApiClient, BatchTransfer, ListEdges, Payout, Repo, RepoError, Scope, TransferToken},
+    EntityTrait,
+};
+
+const TENANT: i32 = 1;
+const OTHER_TENANT: i32 = 2;
+
+/// Every id of `OTHER_TENANT`'s rows, see [`common::seed`].
+const OTHER: i32 = 20;
+
+/// One funded edge of `TENANT` and one of `OTHER_TENANT`.
+async fn setup() -> Arc<Repo> {
+    let db = common::database().await;
+    common::seed(&db, TENANT, 10, true).await;
+    common::seed(&db, OTHER_TENANT, OTHER, true).await;
+    common::repo(db)
+}
+
+fn is_not_found(e: &anyhow::Error) -> bool {
+    e.chain()
+        .any(|cause| matches!(cause.downcast_ref(), Some(RepoError::NotFound(_))))
+}
+
+/// `OTHER_TENANT`'s rows are exactly as seeded.
+async fn assert_untouched(repo: &Repo) {
+    let other = repo.for_tenant(OTHER_TENANT);
+    let edge_wallet = other.get_edge_wallet(OTHER).await.unwrap();
+    assert_eq!(edge_wallet.closed_at, None);
+    assert_eq!(edge_wallet.src_wallet.volume(&edge_wallet.token), 95);
+    assert_eq!(edge_wallet.dst_wallet.volume(&edge_wallet.token), 0);
+    let escrow = Escrows::find_by_id(OTHER)
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(escrow.state, EscrowState::Open);
+    assert!(repo.ledger.stats().iter().all(|node| node.requests == 0));
+}
+
+#[tokio::test]
+async fn tenants_only_read_their_own_edges() {
+    let repo = setup().await;
+    let tenant = repo.for_tenant(TENANT);
+
+    assert_eq!(tenant.get_edge_wallet(10).await.unwrap().edge_id, 10);
+    let e = tenant.get_edge_wallet(OTHER).await.unwrap_err();
+    assert!(is_not_found(&e), "{e:#}");
+
+    let edges = tenant.list_edges(ListEdges::default()).await.unwrap();
+    let edge_ids = edges.items.iter().map(|edge| edge.edge_id);
+    assert_eq!(edge_ids.collect::<Vec<_>>(), [10]);
+
+    let edges = tenant
+        .list_edges(ListEdges {
+            public_key: Some(format!("public-{}", OTHER + 1)),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(edges.items.is_empty());
+}
+
+#[tokio::test]
+async fn tenants_only_read_their_own_wallets() {
+    let repo = setup().await;
+    let tenant = repo.for_tenant(TENANT);
+
+    assert_eq!(tenant.get_wallet_balances(11).await.unwrap().len(), 1);
+    let e = tenant.get_wallet_balances(OTHER + 1).await.unwrap_err();
+    assert!(is_not_found(&e), "{e:#}");
+}
+
+#[tokio::test]
+async fn tenants_cannot_move_other_tenants_tokens() {
+    let repo = setup().await;
+    let tenant = repo.for_tenant(TENANT);
+
+    let e = tenant
+        .clone()
+        .transfer_token(TransferToken { edge_id: OTHER })
+        .await
+        .unwrap_err();
+    assert!(is_not_found(&e), "{e:#}");
+
+    let e = tenant
+        .clone()
+        .batch_transfer(BatchTransfer {
+            from_wallet_id: OTHER + 1,
+            token: format!("token-{}", OTHER + 1),
+            payouts: vec![Payout {
+                to_wallet_id: 12,
+                amount: 1,
+            }],
+        })
+        .await
+        .unwrap_err();
+    assert!(is_not_found(&e), "{e:#}");
+
+    // nor pay their own tokens into another tenant's wallet
+    let e = tenant
+        .clone()
+        .batch_transfer(BatchTransfer {
+            from_wallet_id: 11,
+            token: "token-11".to_string(),
+            payouts: vec![Payout {
+                to_wallet_id: OTHER + 2,
+                amount: 1,
+            }],
+        })
+        .await
+        .unwrap_err();
+    assert!(is_not_found(&e), "{e:#}");
+
+    assert!(tenant.clone().release_escrow(OTHER).await.is_err());
+    assert!(tenant.clone().refund_escrow(OTHER).await.is_err());
+    assert!(tenant.clone().deprovision_edge(OTHER).await.is_err());
+
+    assert_untouched(&repo).await;
+}
+
+#[tokio::test]
+async fn api_clients_only_reach_their_own_tenant() {
+    let repo = setup().await;
+    let authorized = AuthorizedRepo::new(
+        &repo,
+        ApiClient {
+            id: 1,
+            tenant_id: TENANT,
+            name: "client".to_string(),
+            scopes: vec![Scope::Provision, Scope::Transfer, Scope::Read],
+            edge_ids: None,
+        },
+    );
+
+    assert_eq!(authorized.get_edge_wallet(10).await.unwrap().edge_id, 10);
+    let e = authorized.get_edge_wallet(OTHER).await.unwrap_err();
+    assert!(is_not_found(&e), "{e:#}");
+    let e = authorized
+        .transfer_token(TransferToken { edge_id: OTHER })
+        .await
+        .unwrap_err();
+    assert!(is_not_found(&e), "{e:#}");
+    assert!(authorized.release_escrow(OTHER).await.is_err());
+    assert!(authorized.deprovision_edge(OTHER).await.is_err());
+
+    assert_untouched(&repo).await;
+}
@@ -0,0 +1,353 @@
+mod common;
+
+use std::{
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use axum::{body::Bytes, extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+use bc_orm::{
+    entity::{prelude::*, sea_orm_active_enums::*},
+    events::{EventSink, EventType},
+    repo::{
+        sign_webhook, NewWebhook, Repo, RetryPolicy, WebhookSink, DELIVERY_HEADER, EVENT_HEADER,
+        SIGNATURE_HEADER, TIMESTAMP_HEADER,
+    },
+    EntityTrait,
+};
+use reqwest::Client;
+
+const TENANT: i32 = 1;
+const OTHER_TENANT: i32 = 2;
+
+/// A webhook receiver on a local port, answering every request with
+/// `status` and recording it.
+#[derive(Clone)]
+struct Receiver {
+    url: String,
+    status: Arc<AtomicU16>,
+    requests: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+}
+
+impl Receiver {
+    async fn start() -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let receiver = Receiver {
+            url: format!("http://{}/hook", listener.local_addr().unwrap()),
+            status: Arc::new(AtomicU16::new(200)),
+            requests: Default::default(),
+        };
+        let app = Router::new()
+            .route("/hook", post(record))
+            .with_state(receiver.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        receiver
+    }
+
+    fn respond_with(&self, status: StatusCode) {
+        self.status.store(status.as_u16(), Ordering::SeqCst);
+    }
+
+    fn requests(&self) -> Vec<(HeaderMap, Bytes)> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn record(State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    receiver.requests.lock().unwrap().push((headers, body));
+    StatusCode::from_u16(receiver.status.load(Ordering::SeqCst)).unwrap()
+}
+
+/// An empty edge 10 of `TENANT` and 20 of `OTHER_TENANT`, which can be
+/// deprovisioned without the ledger.
+async fn setup() -> Arc<Repo> {
+    let db = common::database().await;
+    common::seed(&db, TENANT, 10, false).await;
+    common::seed(&db, OTHER_TENANT, 20, false).await;
+    common::repo(db)
+}
+
+/// Deprovision edge 10 and hand the resulting events to a [`WebhookSink`]
+/// as the relay would. The relay itself needs a second connection, which an
+/// in-memory database does not have.
+async fn deprovision(repo: &Arc<Repo>) {
+    repo.for_tenant(TENANT).deprovision_edge(10).await.unwrap();
+    let sink = WebhookSink::new(repo.clone());
+    let events = OutboxEvents::find().all(&repo.db).await.unwrap();
+    assert!(!events.is_empty());
+    for event in events {
+        sink.publish(&event.try_into().unwrap()).await.unwrap();
+    }
+}
+
+fn no_retry_delay(max_attempts: i32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay: Duration::ZERO,
+        max_delay: Duration::ZERO,
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+    headers.get(name).unwrap().to_str().unwrap()
+}
+
+#[tokio::test]
+async fn delivers_signed_events_once() {
+    let repo = setup().await;
+    let receiver = Receiver::start().await;
+    let tenant = repo.for_tenant(TENANT);
+    let webhook = tenant
+        .create_webhook(NewWebhook {
+            url: receiver.url.clone(),
+            event_types: vec![EventType::EdgeDeprovisioned],
+        })
+        .await
+        .unwrap();
+
+    deprovision(&repo).await;
+    let client = Client::new();
+    let policy = RetryPolicy::default();
+    assert_eq!(
+        repo.deliver_webhooks(&client, &policy, 10).await.unwrap(),
+        1
+    );
+
+    let requests = receiver.requests();
+    assert_eq!(requests.len(), 1);
+    let (headers, body) = &requests[0];
+    assert_eq!(header(headers, EVENT_HEADER), "edge_deprovisioned");
+    let timestamp = header(headers, TIMESTAMP_HEADER).parse().unwrap();
+    assert_eq!(
+        header(headers, SIGNATURE_HEADER),
+        sign_webhook(&webhook.secret, timestamp, body)
+    );
+    assert_ne!(
+        header(headers, SIGNATURE_HEADER),
+        sign_webhook("whsec_wrong", timestamp, body)
+    );
+
+    let payload: serde_json::Value = serde_json::from_slice(body).unwrap();
+    assert_eq!(payload["type"], "edge_deprovisioned");
+    assert_eq!(payload["edge_id"], 10);
+    assert_eq!(payload["edge_wallet"]["edge_id"], 10);
+    assert!(!payload["edge_wallet"]["closed_at"].is_null());
+
+    let delivery_id: i32 = header(headers, DELIVERY_HEADER).parse().unwrap();
+    let delivery = WebhookDeliveries::find_by_id(delivery_id)
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(delivery.state, WebhookDeliveryState::Delivered);
+    assert_eq!(delivery.attempts, 1);
+
+    // the relay publishing the event again queues nothing new
+    let event = OutboxEvents::find_by_id(delivery.event_id)
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let sink = WebhookSink::new(repo.clone());
+    sink.publish(&event.try_into().unwrap()).await.unwrap();
+    assert_eq!(
+        repo.deliver_webhooks(&client, &policy, 10).await.unwrap(),
+        0
+    );
+    assert_eq!(receiver.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn only_delivers_subscribed_events_of_the_tenant() {
+    let repo = setup().await;
+    let receiver = Receiver::start().await;
+    repo.for_tenant(TENANT)
+        .create_webhook(NewWebhook {
+            url: receiver.url.clone(),
+            event_types: vec![EventType::EscrowOpened],
+        })
+        .await
+        .unwrap();
+    repo.for_tenant(OTHER_TENANT)
+        .create_webhook(NewWebhook {
+            url: receiver.url.clone(),
+            event_types: vec![],
+        })
+        .await
+        .unwrap();
+
+    deprovision(&repo).await;
+    let policy = RetryPolicy::default();
+    let attempted = repo.deliver_webhooks(&Client::new(), &policy, 10).await;
+    assert_eq!(attempted.unwrap(), 0);
+    assert!(receiver.requests().is_empty());
+}
+
+#[tokio::test]
+async fn rejects_non_http_urls() {
+    let repo = setup().await;
+    let created = repo
+        .for_tenant(TENANT)
+        .create_webhook(NewWebhook {
+            url: "ftp://example.com/hook".to_string(),
+            event_types: vec![],
+        })
+        .await;
+    assert!(created.is_err());
+}
+
+#[tokio::test]
+async fn backs_off_after_a_failure() {
+    let repo = setup().await;
+    let receiver = Receiver::start().await;
+    receiver.respond_with(StatusCode::SERVICE_UNAVAILABLE);
+    repo.for_tenant(TENANT)
+        .create_webhook(NewWebhook {
+            url: receiver.url.clone(),
+            event_types: vec![EventType::EdgeDeprovisioned],
+        })
+        .await
+        .unwrap();
+
+    deprovision(&repo).await;
+    let client = Client::new();
+    let policy = RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_secs(60),
+        max_delay: Duration::from_secs(600),
+    };
+    assert_eq!(
+        repo.deliver_webhooks(&client, &policy, 10).await.unwrap(),
+        1
+    );
+    // not due again for a minute
+    assert_eq!(
+        repo.deliver_webhooks(&client, &policy, 10).await.unwrap(),
+        0
+    );
+
+    let delivery = WebhookDeliveries::find()
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(delivery.state, WebhookDeliveryState::Pending);
+    assert_eq!(delivery.attempts, 1);
+    assert!(delivery.next_attempt_at > chrono::Utc::now() + chrono::Duration::seconds(50));
+    assert!(delivery.last_error.unwrap().contains("503"));
+
+    assert_eq!(policy.delay(1), Duration::from_secs(60));
+    assert_eq!(policy.delay(2), Duration::from_secs(120));
+    assert_eq!(policy.delay(5), Duration::from_secs(600));
+}
+
+/// An endpoint that never answers keeps its delivery leased, not the
+/// database: other dispatchers go on and skip it.
+#[tokio::test]
+async fn slow_endpoints_hold_no_locks() {
+    let repo = setup().await;
+    let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    repo.for_tenant(TENANT)
+        .create_webhook(NewWebhook {
+            url: format!("http://{}/hook", silent.local_addr().unwrap()),
+            event_types: vec![EventType::EdgeDeprovisioned],
+        })
+        .await
+        .unwrap();
+
+    deprovision(&repo).await;
+    let policy = no_retry_delay(3);
+    let dispatcher = tokio::spawn({
+        let repo = repo.clone();
+        async move {
+            let client = Client::builder()
+                .timeout(Duration::from_secs(2))
+                .build()
+                .unwrap();
+            repo.deliver_webhooks(&client, &policy, 10).await.unwrap()
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // the in-memory database has a single connection, taken by any open
+    // transaction
+    assert_eq!(
+        repo.deliver_webhooks(&Client::new(), &policy, 10)
+            .await
+            .unwrap(),
+        0
+    );
+    let delivery = WebhookDeliveries::find()
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(delivery.attempts, 0);
+    assert!(delivery.next_attempt_at > chrono::Utc::now() + chrono::Duration::seconds(60));
+
+    assert_eq!(dispatcher.await.unwrap(), 1);
+    let delivery = WebhookDeliveries::find()
+        .one(&repo.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(delivery.state, WebhookDeliveryState::Pending);
+    assert_eq!(delivery.attempts, 1);
+    assert!(delivery.last_error.is_some());
+    drop(silent);
+}
+
+#[tokio::test]
+async fn dead_letters_can_be_redelivered() {
+    let repo = setup().await;
+    let receiver = Receiver::start().await;
+    receiver.respond_with(StatusCode::INTERNAL_SERVER_ERROR);
+    let tenant = repo.for_tenant(TENANT);
+    tenant
+        .create_webhook(NewWebhook {
+            url: receiver.url.clone(),
+            event_types: vec![EventType::EdgeDeprovisioned],
+        })
+        .await
+        .unwrap();
+
+    deprovision(&repo).await;
+    let client = Client::new();
+    let policy = no_retry_delay(3);
+    for _ in 0..3 {
+        assert_eq!(
+            repo.deliver_webhooks(&client, &policy, 10).await.unwrap(),
+            1
+        );
+    }
+    assert_eq!(
+        repo.deliver_webhooks(&client, &policy, 10).await.unwrap(),
+        0
+    );
+    assert_eq!(receiver.requests().len(), 3);
+
+    let dead = tenant.list_dead_letters().await.unwrap();
+    assert_eq!(dead.len(), 1);
+    assert_eq!(dead[0].attempts, 3);
+    assert!(dead[0].last_error.as_ref().unwrap().contains("500"));
+    assert!(repo
+        .for_tenant(OTHER_TENANT)
+        .list_dead_letters()
+        .await
+        .unwrap()
+        .is_empty());
+
+    receiver.respond_with(StatusCode::NO_CONTENT);
+    let delivery = tenant.redeliver_webhook(dead[0].id).await.unwrap();
+    assert_eq!(delivery.state, WebhookDeliveryState::Pending);
+    assert_eq!(
+        repo.deliver_webhooks(&client, &policy, 10).await.unwrap(),
+        1
+    );
+    assert!(tenant.list_dead_letters().await.unwrap().is_empty());
+    // only dead deliveries can be redelivered
+    assert!(tenant.redeliver_webhook(dead[0].id).await.is_err());
+}